@@ -1,2 +1,656 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use rust_iso3166::CountryCode;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    config::AppleMusicCredentials,
+    share_link::{
+        LinkFormat, LinkType, PathParts, ShareLink, ShareLinkError, ShareObject, share_object,
+    },
+    shared_item::{AlbumData, ArtistData, Data, PlaylistData, SongData, matching::Matcher},
+};
+
+use super::{
+    ApiError,
+    authorization::{AuthorizationError, DeveloperToken},
+    provider::{MusicProvider, ProviderInfo},
+    request::RequestExt,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+struct QueryResult<T> {
+    data: Vec<Resource<T>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Resource<T> {
+    id: String,
+    attributes: Option<T>,
+    relationships: Option<Relationships>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Relationships {
+    albums: Option<Relationship<AlbumAttrs>>,
+    artists: Option<Relationship<ArtistAttrs>>,
+    tracks: Option<Relationship<TrackAttrs>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Relationship<T> {
+    data: Vec<Resource<T>>,
+    /// The next page of the relationship, if it has more resources than were included.
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct TrackAttrs {
+    name: String,
+    isrc: Option<String>,
+    duration_in_millis: Option<u64>,
+    artist_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AlbumAttrs {
+    name: String,
+    upc: Option<String>,
+    artwork: Option<Artwork>,
+    artist_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct SearchQuery {
+    results: SearchResults,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct SearchResults {
+    songs: Option<Relationship<TrackAttrs>>,
+    albums: Option<Relationship<AlbumAttrs>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct ArtistAttrs {
+    name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct PlaylistAttrs {
+    name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Artwork {
+    url: String,
+    width: u16,
+}
+
 #[derive(Clone)]
-pub struct AppleMusicApi {}
+pub struct AppleMusicApi {
+    client: Client,
+    creds: AppleMusicCredentials,
+    developer_token: Arc<RwLock<DeveloperToken>>,
+    matcher: Matcher,
+}
+
+impl AppleMusicApi {
+    const BASE_URL: &'static str = "https://api.music.apple.com/v1/catalog";
+    const MAX_SEARCH_CANDIDATES: usize = 5;
+
+    pub fn new(
+        client: &Client,
+        credentials: &AppleMusicCredentials,
+        matcher: &Matcher,
+    ) -> Result<Self, AuthorizationError> {
+        Ok(Self {
+            client: client.clone(),
+            creds: credentials.clone(),
+            developer_token: Arc::new(RwLock::new(DeveloperToken::new(credentials)?)),
+            matcher: matcher.clone(),
+        })
+    }
+
+    async fn get_bearer_token(&self) -> Result<String, AuthorizationError> {
+        {
+            let token_ro = self.developer_token.read().await;
+            if !token_ro.is_expired() {
+                return Ok(token_ro.token.clone());
+            }
+        }
+        let mut token_w = self.developer_token.write().await;
+        token_w.refresh(&self.creds)?;
+        Ok(token_w.token.clone())
+    }
+
+    /// Apple Music addresses its catalog by storefront, which is the lower case alpha2 code of
+    /// the country.
+    fn storefront(country_code: &CountryCode) -> String {
+        country_code.alpha2.to_ascii_lowercase()
+    }
+
+    async fn query<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T, ApiError> {
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    pub async fn get_song_data(&self, song_link: &ShareLink) -> Result<SongData, ApiError> {
+        if song_link.link_type != LinkType::AppleMusic || song_link.share_obj != ShareObject::Song
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let results: QueryResult<TrackAttrs> = self
+            .query(&format!(
+                "{}/{}/songs/{}?include=albums,artists",
+                Self::BASE_URL,
+                Self::storefront(&song_link.country_code),
+                song_link.id
+            ))
+            .await?;
+
+        let song = results
+            .data
+            .into_iter()
+            .next()
+            .ok_or(ApiError::UnsuccessfulConversion)?;
+        let song_attrs = song.attributes.ok_or(ApiError::IncorrectAttributes)?;
+        let song_isrc = song_attrs.isrc.ok_or(ApiError::IncorrectAttributes)?;
+        let song_dur = song_attrs.duration_in_millis.unwrap_or(0) / 1000;
+
+        let mut albums = Vec::new();
+        let mut artists = Vec::new();
+
+        if let Some(relationships) = song.relationships {
+            for album in relationships.albums.map(|rel| rel.data).unwrap_or_default() {
+                if let Some(attrs) = album.attributes {
+                    albums.push(AlbumData::with_limited_info(
+                        &attrs.name,
+                        &attrs.upc.unwrap_or_default(),
+                    ));
+                }
+            }
+            for artist in relationships.artists.map(|rel| rel.data).unwrap_or_default() {
+                if let Some(attrs) = artist.attributes {
+                    artists.push(ArtistData::without_albums(&attrs.name));
+                }
+            }
+        }
+
+        if albums.is_empty() {
+            return Err(ApiError::IncorrectAttributes);
+        }
+
+        Ok(SongData::new(
+            &song_attrs.name,
+            &song_isrc,
+            song_dur,
+            albums,
+            artists,
+        ))
+    }
+
+    pub async fn get_album_data(&self, album_link: &ShareLink) -> Result<AlbumData, ApiError> {
+        if album_link.link_type != LinkType::AppleMusic
+            || album_link.share_obj != ShareObject::Album
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let results: QueryResult<AlbumAttrs> = self
+            .query(&format!(
+                "{}/{}/albums/{}?include=tracks,artists",
+                Self::BASE_URL,
+                Self::storefront(&album_link.country_code),
+                album_link.id
+            ))
+            .await?;
+
+        let album = results
+            .data
+            .into_iter()
+            .next()
+            .ok_or(ApiError::UnsuccessfulConversion)?;
+        let album_attrs = album.attributes.ok_or(ApiError::IncorrectAttributes)?;
+        let album_upc = album_attrs.upc.ok_or(ApiError::IncorrectAttributes)?;
+
+        let mut songs = Vec::new();
+        let mut artists = Vec::new();
+
+        if let Some(relationships) = album.relationships {
+            for track in relationships.tracks.map(|rel| rel.data).unwrap_or_default() {
+                if let Some(attrs) = track.attributes {
+                    songs.push(SongData::new(
+                        &attrs.name,
+                        &attrs.isrc.unwrap_or_default(),
+                        attrs.duration_in_millis.unwrap_or(0) / 1000,
+                        Vec::new(),
+                        Vec::new(),
+                    ));
+                }
+            }
+            for artist in relationships.artists.map(|rel| rel.data).unwrap_or_default() {
+                if let Some(attrs) = artist.attributes {
+                    artists.push(ArtistData::without_albums(&attrs.name));
+                }
+            }
+        }
+
+        Ok(AlbumData::new(&album_attrs.name, &album_upc, songs, artists))
+    }
+
+    pub async fn get_playlist_data(
+        &self,
+        playlist_link: &ShareLink,
+    ) -> Result<PlaylistData, ApiError> {
+        if playlist_link.link_type != LinkType::AppleMusic
+            || playlist_link.share_obj != ShareObject::Playlist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let results: QueryResult<PlaylistAttrs> = self
+            .query(&format!(
+                "{}/{}/playlists/{}?include=tracks",
+                Self::BASE_URL,
+                Self::storefront(&playlist_link.country_code),
+                playlist_link.id
+            ))
+            .await?;
+
+        let playlist = results
+            .data
+            .into_iter()
+            .next()
+            .ok_or(ApiError::UnsuccessfulConversion)?;
+        let playlist_attrs = playlist.attributes.ok_or(ApiError::IncorrectAttributes)?;
+
+        let mut songs = Vec::new();
+        let mut truncated = false;
+        if let Some(relationships) = playlist.relationships {
+            // only the first page of tracks is included
+            let tracks = relationships.tracks.map(|rel| {
+                truncated = rel.next.is_some();
+                rel.data
+            });
+            for track in tracks.unwrap_or_default() {
+                if let Some(attrs) = track.attributes {
+                    songs.push(SongData::new(
+                        &attrs.name,
+                        &attrs.isrc.unwrap_or_default(),
+                        attrs.duration_in_millis.unwrap_or(0) / 1000,
+                        Vec::new(),
+                        Vec::new(),
+                    ));
+                }
+            }
+        }
+
+        let mut playlist = PlaylistData::new(&playlist_attrs.name, songs);
+        playlist.truncated = truncated;
+        Ok(playlist)
+    }
+
+    pub async fn get_song_link(
+        &self,
+        song_data: &SongData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let results: QueryResult<TrackAttrs> = self
+            .query(&format!(
+                "{}/{}/songs?filter[isrc]={}",
+                Self::BASE_URL,
+                Self::storefront(country_code),
+                song_data.isrc
+            ))
+            .await?;
+
+        match results.data.first() {
+            Some(song) => Ok(ShareLink::new(
+                LinkType::AppleMusic,
+                ShareObject::Song,
+                &song.id,
+                country_code,
+            )),
+            None => Err(ApiError::UnsuccessfulConversion),
+        }
+    }
+
+    pub async fn get_album_link(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let results: QueryResult<AlbumAttrs> = self
+            .query(&format!(
+                "{}/{}/albums?filter[upc]={}",
+                Self::BASE_URL,
+                Self::storefront(country_code),
+                album_data.upc
+            ))
+            .await?;
+
+        match results.data.first() {
+            Some(album) => Ok(ShareLink::new(
+                LinkType::AppleMusic,
+                ShareObject::Album,
+                &album.id,
+                country_code,
+            )),
+            None => Err(ApiError::UnsuccessfulConversion),
+        }
+    }
+
+    /// Searches for a song by its title and artist. This is the fallback for songs, which can
+    /// not be found by their ISRC, e.g. because of regional re-releases.
+    pub async fn search_song_link(
+        &self,
+        song_data: &SongData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let artist = match song_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("{} {}", song_data.norm_name(), artist);
+
+        let results: SearchQuery = self
+            .query(&format!(
+                "{}/{}/search?term={}&types=songs&limit={}",
+                Self::BASE_URL,
+                Self::storefront(country_code),
+                urlencoding::encode(&query),
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .await?;
+
+        let songs = results
+            .results
+            .songs
+            .map(|rel| rel.data)
+            .unwrap_or_default();
+        for song in songs {
+            let attrs = match song.attributes {
+                Some(attrs) => attrs,
+                None => continue,
+            };
+            let candidate = SongData::new(
+                &attrs.name,
+                "",
+                attrs.duration_in_millis.unwrap_or(0) / 1000,
+                Vec::new(),
+                vec![ArtistData::without_albums(
+                    &attrs.artist_name.unwrap_or_default(),
+                )],
+            );
+
+            if self.matcher.songs_match(song_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::AppleMusic,
+                    ShareObject::Song,
+                    &song.id,
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
+    /// Searches for an album by its title and artist. This is the fallback for albums, which can
+    /// not be found by their UPC.
+    pub async fn search_album_link(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let artist = match album_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("{} {}", album_data.norm_name(), artist);
+
+        let results: SearchQuery = self
+            .query(&format!(
+                "{}/{}/search?term={}&types=albums&limit={}",
+                Self::BASE_URL,
+                Self::storefront(country_code),
+                urlencoding::encode(&query),
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .await?;
+
+        let albums = results
+            .results
+            .albums
+            .map(|rel| rel.data)
+            .unwrap_or_default();
+        for album in albums {
+            let attrs = match album.attributes {
+                Some(attrs) => attrs,
+                None => continue,
+            };
+            let candidate = AlbumData::new(
+                &attrs.name,
+                "",
+                Vec::new(),
+                vec![ArtistData::without_albums(
+                    &attrs.artist_name.unwrap_or_default(),
+                )],
+            );
+
+            if self.matcher.albums_match(album_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::AppleMusic,
+                    ShareObject::Album,
+                    &album.id,
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
+    const PREFERRED_MAX_IMAGE_SIZE: u16 = 800;
+
+    pub async fn get_cover_art(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        let results: QueryResult<AlbumAttrs> = self
+            .query(&format!(
+                "{}/{}/albums?filter[upc]={}",
+                Self::BASE_URL,
+                Self::storefront(country_code),
+                album_data.upc
+            ))
+            .await?;
+
+        let artwork = match results
+            .data
+            .into_iter()
+            .next()
+            .and_then(|album| album.attributes)
+            .and_then(|attrs| attrs.artwork)
+        {
+            Some(artwork) => artwork,
+            None => return Ok(String::new()),
+        };
+
+        // Apple Music does not return a list of images but a url template, into which the
+        // desired dimensions are inserted.
+        let size = artwork.width.min(Self::PREFERRED_MAX_IMAGE_SIZE);
+        Ok(artwork
+            .url
+            .replace("{w}", &size.to_string())
+            .replace("{h}", &size.to_string()))
+    }
+}
+
+#[async_trait]
+impl MusicProvider for AppleMusicApi {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            link_type: LinkType::AppleMusic,
+            url: "https://music.apple.com",
+            asset_name: "apple_music",
+            parses: &[ShareObject::Song, ShareObject::Album, ShareObject::Playlist],
+            converts_to: &[ShareObject::Song, ShareObject::Album, ShareObject::Playlist],
+        }
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Song => Ok(Data::Song(self.get_song_data(link).await?)),
+            ShareObject::Album => Ok(Data::Album(self.get_album_data(link).await?)),
+            ShareObject::Artist => Err(ApiError::UnsupportedFeature),
+            ShareObject::Playlist => Ok(Data::Playlist(self.get_playlist_data(link).await?)),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.get_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.get_album_link(album_data, country_code).await,
+            Data::Artist(_) => Err(ApiError::UnsupportedFeature),
+            // playlists are user specific and can only be converted track by track
+            Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn search_data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.search_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.search_album_link(album_data, country_code).await,
+            // artists are already looked up by searching for them
+            Data::Artist(_) | Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn get_artwork(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        let album_data = match data {
+            Data::Song(song_data) => song_data
+                .albums
+                .first()
+                .ok_or(ApiError::IncorrectAttributes)?,
+            Data::Album(album_data) => album_data,
+            Data::Artist(_) | Data::Playlist(_) => return Err(ApiError::UnsupportedFeature),
+        };
+        self.get_cover_art(album_data, country_code).await
+    }
+}
+
+pub struct AppleMusicLinks;
+
+impl LinkFormat for AppleMusicLinks {
+    fn link_type(&self) -> LinkType {
+        LinkType::AppleMusic
+    }
+
+    fn name(&self) -> &'static str {
+        "AppleMusic"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["music.apple.com", "geo.music.apple.com", "itunes.apple.com"]
+    }
+
+    /// `/[{country}/]{kind}/[{name}/]{id}`, where the country is the storefront. Old iTunes links
+    /// prefix the ID with `id`.
+    fn parse_path(&self, url: &Url, segments: &[&str]) -> Result<PathParts, ShareLinkError> {
+        let (country_code, segments) = match segments {
+            [country, rest @ ..] if country.len() == 2 => (
+                Some(
+                    rust_iso3166::from_alpha2(&country.to_ascii_uppercase())
+                        .ok_or(ShareLinkError::MalformedOrInvalidLink)?,
+                ),
+                rest,
+            ),
+            _ => (None, segments),
+        };
+        let (share_obj, id) = match segments {
+            [kind, _, id] | [kind, id] => {
+                let id = match id.strip_prefix("id") {
+                    Some(number) if number.chars().all(|c| c.is_ascii_digit()) => number,
+                    _ => id,
+                };
+                (share_object(kind)?, id)
+            }
+            _ => return Err(ShareLinkError::MalformedOrInvalidLink),
+        };
+        // songs are usually shared as their album with the song in the `i` parameter
+        let song_id = url
+            .query_pairs()
+            .find(|(key, _)| key == "i")
+            .map(|(_, song_id)| song_id);
+        match song_id {
+            Some(song_id) if share_obj == ShareObject::Album => {
+                Ok((country_code, ShareObject::Song, song_id.into_owned()))
+            }
+            _ => Ok((country_code, share_obj, id.to_string())),
+        }
+    }
+
+    fn kind(&self, share_obj: ShareObject) -> &'static str {
+        match share_obj {
+            ShareObject::Song => "song",
+            ShareObject::Album => "album",
+            ShareObject::Artist => "artist",
+            ShareObject::Playlist => "playlist",
+        }
+    }
+
+    fn to_url(&self, link: &ShareLink) -> String {
+        format!(
+            "https://music.apple.com/{}/{}/{}",
+            link.country_code.alpha2.to_ascii_lowercase(),
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn to_app_uri(&self, link: &ShareLink) -> String {
+        format!(
+            "music://music.apple.com/{}/{}/{}",
+            link.country_code.alpha2.to_ascii_lowercase(),
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn android_package(&self) -> &'static str {
+        "com.apple.android.music"
+    }
+
+    /// The Android app of Apple Music is opened by its web links instead of the app URI.
+    fn android_intent_uri(&self, link: &ShareLink) -> String {
+        self.to_url(link)
+    }
+}
@@ -3,11 +3,12 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::config::ClientCredentials;
+use crate::config::{AppleMusicCredentials, ClientCredentials};
 
 #[derive(Debug, Error)]
 pub enum AuthorizationError {
@@ -15,6 +16,10 @@ pub enum AuthorizationError {
     RequestError(#[from] reqwest::Error),
     #[error(transparent)]
     ParsingError(#[from] serde_json::Error),
+    #[error("Could not read private key: {0}")]
+    PrivateKeyError(#[from] std::io::Error),
+    #[error(transparent)]
+    SigningError(#[from] jsonwebtoken::errors::Error),
 }
 
 pub struct AccessToken {
@@ -48,6 +53,7 @@ impl AccessToken {
             .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
             .send()
             .await?
+            .error_for_status()?
             .text()
             .await?;
         let body: ResponseBody = serde_json::from_str(&response)?;
@@ -82,3 +88,64 @@ impl AccessToken {
         return true;
     }
 }
+
+/// A developer token for the Apple Music API. Unlike the access tokens of Spotify and Tidal it is
+/// not requested from an authorization endpoint, but is a JWT signed locally with the ES256
+/// private key of the developer account.
+pub struct DeveloperToken {
+    pub token: String,
+    expiry: u64,
+}
+
+impl DeveloperToken {
+    // Apple accepts tokens which are valid for at most six months. A shorter lifetime keeps the
+    // damage low, in case a token gets leaked.
+    const LIFETIME: u64 = 60 * 60 * 24 * 30;
+
+    pub fn new(credentials: &AppleMusicCredentials) -> Result<Self, AuthorizationError> {
+        #[derive(Serialize, Deserialize)]
+        struct Claims {
+            iss: String,
+            iat: u64,
+            exp: u64,
+        }
+
+        let time_now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Don't mess with the system clock. Use a reliable NTP server pls.")
+            .as_secs();
+
+        let private_key = std::fs::read(&credentials.private_key_path)?;
+        let encoding_key = EncodingKey::from_ec_pem(&private_key)?;
+
+        let mut header = Header::new(Algorithm::ES256);
+        header.kid = Some(credentials.key_id.clone());
+
+        let claims = Claims {
+            iss: credentials.team_id.clone(),
+            iat: time_now,
+            exp: time_now + Self::LIFETIME,
+        };
+
+        Ok(Self {
+            token: jsonwebtoken::encode(&header, &claims, &encoding_key)?,
+            expiry: time_now + Self::LIFETIME - 5,
+        })
+    }
+
+    pub fn refresh(&mut self, credentials: &AppleMusicCredentials) -> Result<(), AuthorizationError> {
+        let refreshed_token = DeveloperToken::new(credentials)?;
+        self.token = refreshed_token.token;
+        self.expiry = refreshed_token.expiry;
+        Ok(())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let time_now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Don't mess with the system clock. Use a reliable NTP server pls.")
+            .as_secs();
+
+        time_now >= self.expiry
+    }
+}
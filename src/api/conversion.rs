@@ -1,20 +1,40 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, pin::pin, sync::Arc, time::Duration};
 
+use futures::{
+    future::join_all,
+    stream::{self, StreamExt},
+};
 use reqwest::Client;
+use rust_iso3166::CountryCode;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::{sync::Mutex, time::MissedTickBehavior};
 
 use crate::{
-    api::ApiClient,
-    config::Credentials,
-    share_link::{LinkType, ShareLink, ShareLinkError},
-    shared_item::Data,
+    config::Config,
+    share_link::{LinkType, ShareLink, ShareLinkError, ShareObject},
+    shared_item::{Data, PlaylistData, matching::Matcher},
+    store::MappingStore,
 };
 
+use cache::{CacheKey, ConversionCache};
+use circuit_breaker::CircuitBreaker;
+use single_flight::SingleFlight;
+
 use super::{
-    ApiError, apple_music::AppleMusicApi, deezer::DeezerApi, spotify::SpotifyApi, tidal::TidalApi,
+    ApiError,
+    apple_music::AppleMusicApi,
+    deezer::DeezerApi,
+    provider::{MusicProvider, ProviderRegistry},
+    spotify::SpotifyApi,
+    tidal::TidalApi,
 };
 
+pub mod availability;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod single_flight;
+
 #[derive(Debug, Error)]
 pub enum ConversionError {
     #[error(transparent)]
@@ -22,12 +42,104 @@ pub enum ConversionError {
     #[error(transparent)]
     Link(#[from] ShareLinkError),
 }
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConversionResults {
     pub results: Vec<Link>,
+    /// The streaming services for which the conversion failed.
+    #[serde(default)]
+    pub errors: Vec<ProviderError>,
+}
+
+impl ConversionResults {
+    /// Keeps only the results and errors of the given streaming services, in their order.
+    fn select(mut self, providers: &[&dyn MusicProvider]) -> Self {
+        let names: Vec<String> = providers
+            .iter()
+            .map(|provider| provider.info().link_type.to_string())
+            .collect();
+        let position = |provider: &str| names.iter().position(|name| name == provider);
+
+        self.results
+            .retain(|link| position(&link.provider).is_some());
+        self.results.sort_by_key(|link| position(&link.provider));
+        self.errors.retain(|err| position(&err.provider).is_some());
+        self.errors.sort_by_key(|err| position(&err.provider));
+        self
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProviderError {
+    pub provider: String,
+    pub reason: ErrorReason,
+    pub message: String,
+}
+
+/// Machine-readable reason why a streaming service could not provide a link.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorReason {
+    NotFound,
+    AuthFailure,
+    RateLimited,
+    Timeout,
+    Unsupported,
+    ProviderError,
+    /// The streaming service failed repeatedly and is skipped for a while.
+    Unavailable,
+}
+
+impl From<&ApiError> for ErrorReason {
+    fn from(err: &ApiError) -> Self {
+        match err {
+            ApiError::UnsuccessfulConversion => Self::NotFound,
+            ApiError::UnsupportedFeature => Self::Unsupported,
+            ApiError::AuthorizationError(_) | ApiError::Unauthorized => Self::AuthFailure,
+            ApiError::RateLimited { .. } => Self::RateLimited,
+            ApiError::UnexpectedStatus(404) => Self::NotFound,
+            ApiError::UnexpectedStatus(_) | ApiError::ServiceError { .. } => Self::ProviderError,
+            ApiError::RequestError(err) if err.is_timeout() => Self::Timeout,
+            ApiError::RequestError(err) => match err.status().map(|status| status.as_u16()) {
+                Some(401 | 403) => Self::AuthFailure,
+                Some(404) => Self::NotFound,
+                Some(429) => Self::RateLimited,
+                _ => Self::ProviderError,
+            },
+            ApiError::ParsingError(_)
+            | ApiError::UnsuitableLink
+            | ApiError::IncorrectAttributes => Self::ProviderError,
+        }
+    }
+}
+
+impl ProviderError {
+    fn new(link_type: LinkType, err: &ApiError) -> Self {
+        Self {
+            provider: link_type.to_string(),
+            reason: ErrorReason::from(err),
+            message: err.to_string(),
+        }
+    }
+
+    fn unavailable(link_type: LinkType) -> Self {
+        Self {
+            provider: link_type.to_string(),
+            reason: ErrorReason::Unavailable,
+            message: String::from("The streaming service is temporarily unavailable."),
+        }
+    }
+
+    fn timeout(link_type: LinkType) -> Self {
+        Self {
+            provider: link_type.to_string(),
+            reason: ErrorReason::Timeout,
+            message: String::from("The streaming service did not answer in time."),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Link {
     pub provider: String,
     pub r#type: String,
@@ -35,177 +147,673 @@ pub struct Link {
     pub display_name: String,
     pub url: String,
     pub artwork: String,
+    /// Whether the link was found by searching for the metadata, because the lookup by ISRC or
+    /// UPC was unsuccessful.
+    #[serde(rename = "fuzzyMatch", default)]
+    pub fuzzy_match: bool,
+    /// How certain it is that the link points to the requested item, between 0 and 1.
+    #[serde(default)]
+    pub confidence: f64,
+    #[serde(rename = "matchedBy", default)]
+    pub matched_by: Option<MatchMethod>,
+    /// The identifiers of the linked item.
+    #[serde(default)]
+    pub identifiers: Identifiers,
+    /// How to open the linked item in the app of the streaming service.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub app: Option<AppLink>,
 }
 
 impl Link {
-    pub fn new(link: &ShareLink, data: &Data, artwork: &str) -> Self {
+    pub fn new(
+        link: &ShareLink,
+        data: &Data,
+        artwork: &str,
+        matched_by: MatchMethod,
+        confidence: f64,
+    ) -> Self {
         Self {
             provider: link.link_type.to_string(),
             r#type: data.get_type(),
             display_name: data.get_display_name(),
             url: link.to_url(),
             artwork: artwork.to_owned(),
+            fuzzy_match: matched_by == MatchMethod::MetadataSearch,
+            confidence,
+            matched_by: Some(matched_by),
+            identifiers: Identifiers::of(data),
+            app: Some(AppLink::of(link)),
         }
     }
 }
 
+/// The way a link was found on the target provider.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchMethod {
+    Isrc,
+    Upc,
+    MetadataSearch,
+}
+
+impl MatchMethod {
+    /// The method used to look up the data by its identifier. Artists have no identifier and are
+    /// always searched for by their name.
+    fn lookup(data: &Data) -> Self {
+        match data {
+            Data::Song(_) => Self::Isrc,
+            Data::Album(_) => Self::Upc,
+            Data::Artist(_) | Data::Playlist(_) => Self::MetadataSearch,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Identifiers {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub isrc: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub upc: Option<String>,
+}
+
+impl Identifiers {
+    fn of(data: &Data) -> Self {
+        let non_empty = |id: &str| (!id.is_empty()).then(|| id.to_owned());
+        match data {
+            Data::Song(song_data) => Self {
+                isrc: non_empty(&song_data.isrc),
+                upc: None,
+            },
+            Data::Album(album_data) => Self {
+                isrc: None,
+                upc: non_empty(&album_data.upc),
+            },
+            Data::Artist(_) | Data::Playlist(_) => Self::default(),
+        }
+    }
+}
+
+/// Opens the linked item in the app of the streaming service instead of the browser.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct AppLink {
+    /// The URI handled by the app, e.g. `spotify:track:{id}`.
+    pub uri: String,
+    #[serde(rename = "androidPackage")]
+    pub android_package: String,
+    /// An Android intent URI that opens the app and falls back to the web link if the app is not
+    /// installed.
+    #[serde(rename = "androidIntent")]
+    pub android_intent: String,
+}
+
+impl AppLink {
+    pub fn of(link: &ShareLink) -> Self {
+        let format = link.link_type.format();
+        let uri = format.to_app_uri(link);
+        let android_package = format.android_package();
+        let intent_uri = format.android_intent_uri(link);
+        let (scheme, data) = intent_uri
+            .split_once(':')
+            .expect("App URIs should always have a scheme.");
+        Self {
+            android_intent: format!(
+                "intent:{}#Intent;scheme={};package={};S.browser_fallback_url={};end",
+                data,
+                scheme,
+                android_package,
+                urlencoding::encode(&link.to_url())
+            ),
+            android_package: android_package.to_owned(),
+            uri,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaylistConversionResults {
+    pub results: Vec<PlaylistLink>,
+    /// The streaming services for which the conversion failed.
+    #[serde(default)]
+    pub errors: Vec<ProviderError>,
+    /// Whether the playlist is longer than the streaming service allows to convert, in which case
+    /// only its first tracks are converted.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+/// The tracks of a playlist converted to a single provider. Tracks that could not be found on
+/// the provider are listed in `unmatched`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlaylistLink {
+    pub provider: String,
+    pub r#type: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub tracks: Vec<Link>,
+    pub unmatched: Vec<UnmatchedTrack>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnmatchedTrack {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub isrc: String,
+}
+
 #[derive(Clone)]
 pub struct ApiClients {
-    spotify: Option<SpotifyApi>,
-    tidal: Option<TidalApi>,
-    deezer: Option<DeezerApi>,
-    apple_music: Option<AppleMusicApi>,
+    providers: Arc<ProviderRegistry>,
+    matcher: Matcher,
+    /// How long a single streaming service may take to answer during a conversion.
+    timeout: Duration,
+    cache: Arc<ConversionCache>,
+    store: Arc<MappingStore>,
+    in_flight: Arc<SingleFlight<(CacheKey, Vec<LinkType>), String>>,
+    breakers: Arc<HashMap<LinkType, CircuitBreaker>>,
+    /// The market of links that do not contain a country.
+    default_country: CountryCode,
 }
 
 impl ApiClients {
-    pub async fn new(client: &Client, credentials: Credentials) -> Self {
-        let mut spotify = None;
-        let mut tidal = None;
+    // the number of failed conversions in a row after which a streaming service is skipped, and
+    // for how long
+    const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+    const BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+    pub async fn new(client: &Client, config: &Config) -> Self {
+        let credentials = config.credentials.clone().expect("No credentials found.");
+        let matcher = Matcher::new(&config.matching);
+        let mut providers = ProviderRegistry::new();
 
         if credentials.spotify.is_some() {
-            spotify = Some(SpotifyApi::new(client, &credentials.spotify.unwrap()).await);
+            providers
+                .register(SpotifyApi::new(client, &credentials.spotify.unwrap(), &matcher).await);
         }
         if credentials.tidal.is_some() {
-            tidal = Some(
-                TidalApi::new(client, &credentials.tidal.unwrap())
+            providers.register(
+                TidalApi::new(client, &credentials.tidal.unwrap(), &matcher)
                     .await
                     .unwrap(),
             );
         }
+        providers.register(DeezerApi::new(client, &matcher));
+        // a missing or invalid key only leaves out Apple Music instead of stopping the server
+        if let Some(apple_music) = &credentials.apple_music {
+            match AppleMusicApi::new(client, apple_music, &matcher) {
+                Ok(apple_music) => providers.register(apple_music),
+                Err(err) => eprintln!("Apple Music is not available: {}", err),
+            }
+        }
+
+        Self::with_providers(providers, config)
+    }
+
+    /// Uses the given streaming services instead of the ones configured by the credentials.
+    pub fn with_providers(providers: ProviderRegistry, config: &Config) -> Self {
+        let breakers = providers
+            .iter()
+            .map(|provider| {
+                let breaker =
+                    CircuitBreaker::new(Self::BREAKER_FAILURE_THRESHOLD, Self::BREAKER_COOLDOWN);
+                (provider.info().link_type, breaker)
+            })
+            .collect();
 
         Self {
-            spotify,
-            tidal,
-            deezer: Some(DeezerApi::new(client)),
-            apple_music: None,
+            providers: Arc::new(providers),
+            matcher: Matcher::new(&config.matching),
+            timeout: Duration::from_secs(config.provider_timeout),
+            cache: Arc::new(ConversionCache::new(&config.cache)),
+            store: Arc::new(
+                MappingStore::open(&config.database_path).expect("Could not open the database."),
+            ),
+            in_flight: Arc::new(SingleFlight::new()),
+            breakers: Arc::new(breakers),
+            default_country: rust_iso3166::from_alpha2(
+                &config.default_country.to_ascii_uppercase(),
+            )
+            .expect("default_country is not an ISO 3166-1 alpha-2 code."),
         }
     }
 
-    pub fn get_supported_clients(&self) -> HashMap<String, ApiClient> {
-        let mut supported_apis: HashMap<String, ApiClient> = HashMap::new();
+    pub fn providers(&self) -> &ProviderRegistry {
+        &self.providers
+    }
 
-        let cloned = self.clone();
-        if self.spotify.is_some() {
-            supported_apis.insert(
-                LinkType::Spotify.to_string(),
-                ApiClient::Spotify(cloned.spotify.unwrap()),
-            );
-        }
-        if self.tidal.is_some() {
-            supported_apis.insert(
-                LinkType::Tidal.to_string(),
-                ApiClient::Tidal(cloned.tidal.unwrap()),
-            );
-        }
-        if self.deezer.is_some() {
-            supported_apis.insert(
-                LinkType::Deezer.to_string(),
-                ApiClient::Deezer(cloned.deezer.unwrap()),
-            );
-        }
-        if self.apple_music.is_some() {
-            supported_apis.insert(
-                LinkType::AppleMusic.to_string(),
-                ApiClient::AppleMusic(cloned.apple_music.unwrap()),
-            );
-        }
-        return supported_apis;
+    /// Whether the streaming service is configured and has not been failing recently.
+    pub fn is_available(&self, link_type: LinkType) -> bool {
+        self.breakers
+            .get(&link_type)
+            .is_some_and(CircuitBreaker::is_available)
+    }
+}
+
+/// Which streaming services a link is converted to, in which market and in which order they are
+/// listed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionOptions {
+    /// The market in which the link is converted, instead of the one of the link.
+    pub country: Option<CountryCode>,
+    /// Only these streaming services are queried. `None` queries every configured one.
+    pub to: Option<Vec<LinkType>>,
+    /// These streaming services are listed first, in the given order. The remaining ones follow
+    /// in the order of `to` or, without it, in the order in which they are configured.
+    pub order: Vec<LinkType>,
+}
+
+impl ConversionOptions {
+    /// The configured streaming services to query, in the order of the results.
+    fn targets<'a>(&self, providers: &'a ProviderRegistry) -> Vec<&'a dyn MusicProvider> {
+        let mut targets: Vec<&dyn MusicProvider> = providers
+            .iter()
+            .map(|provider| provider.as_ref())
+            .filter(|provider| {
+                let link_type = provider.info().link_type;
+                self.to.as_ref().is_none_or(|to| to.contains(&link_type))
+            })
+            .collect();
+        // the sort is stable, so providers without a preference keep the configured order
+        targets.sort_by_key(|provider| {
+            let link_type = provider.info().link_type;
+            let position = |link_types: &[LinkType]| {
+                link_types
+                    .iter()
+                    .position(|preferred| *preferred == link_type)
+                    .unwrap_or(usize::MAX)
+            };
+            (
+                position(&self.order),
+                position(self.to.as_deref().unwrap_or_default()),
+            )
+        });
+        targets
     }
 }
 
 pub async fn convert(url: &str, api_clients: Arc<ApiClients>) -> Result<String, ConversionError> {
-    let share_link = ShareLink::from_url(&url).await?;
-    let supported_apis = api_clients.get_supported_clients();
-
-    if !supported_apis.contains_key(&share_link.link_type.to_string()) {
-        return Err(ConversionError::ApiClient(ApiError::UnsupportedFeature));
-    }
-
-    let data = supported_apis
-        .get(&share_link.link_type.to_string())
-        .unwrap()
-        .link_to_data(&share_link)
-        .await?;
-
-    let spotify_result = match supported_apis.get(&LinkType::Spotify.to_string()) {
-        Some(client) => {
-            let spotify_link = client.data_to_link(&data, &share_link.country_code).await;
-            let image_link = client
-                .get_artwork(&data, &share_link.country_code)
-                .await
-                .unwrap_or(String::new());
-            if spotify_link.is_ok() {
-                let spotify_link = spotify_link.unwrap();
-                let spotify_data = client.link_to_data(&spotify_link).await.unwrap();
-                Some(Link::new(&spotify_link, &spotify_data, &image_link))
-            } else {
-                None
-            }
+    convert_with_options(url, &ConversionOptions::default(), api_clients).await
+}
+
+pub async fn convert_with_options(
+    url: &str,
+    options: &ConversionOptions,
+    api_clients: Arc<ApiClients>,
+) -> Result<String, ConversionError> {
+    let (share_link, source) = parse_share_link(url, options.country, &api_clients).await?;
+    let targets = options.targets(&api_clients.providers);
+    let target_types = targets
+        .iter()
+        .map(|target| target.info().link_type)
+        .collect();
+
+    // identical links that are converted at the same time share a single conversion
+    api_clients
+        .in_flight
+        .run((CacheKey::from(&share_link), target_types), || {
+            convert_share_link(&share_link, source, &targets, &api_clients)
+        })
+        .await
+}
+
+/// Parses the share link and returns it together with its streaming service. The `country`
+/// replaces the one of the link.
+async fn parse_share_link<'a>(
+    url: &str,
+    country: Option<CountryCode>,
+    api_clients: &'a ApiClients,
+) -> Result<(ShareLink, &'a dyn MusicProvider), ConversionError> {
+    let mut share_link =
+        ShareLink::from_url_with_default_country(url, &api_clients.default_country).await?;
+    if let Some(country) = country {
+        share_link.country_code = country;
+    }
+    match api_clients.providers.get(share_link.link_type) {
+        Some(source) if source.info().parses.contains(&share_link.share_obj) => {
+            Ok((share_link, source.as_ref()))
         }
-        None => None,
-    };
+        _ => Err(ConversionError::ApiClient(ApiError::UnsupportedFeature)),
+    }
+}
+
+async fn convert_share_link(
+    share_link: &ShareLink,
+    source: &dyn MusicProvider,
+    targets: &[&dyn MusicProvider],
+    api_clients: &ApiClients,
+) -> Result<String, ConversionError> {
+    if let Some(results) = api_clients.cache.get(share_link) {
+        let results = results.select(targets);
+        return Ok(
+            serde_json::to_string(&results).expect("Conversion result should always be valid.")
+        );
+    }
+
+    let data = source.link_to_data(share_link).await?;
+    if let Some(identifier) = identifier(&data) {
+        api_clients
+            .store
+            .insert_link(identifier, share_link)
+            .await
+            .ok();
+    }
+
+    // playlists are not cached, since they are edited far more often than songs or albums
+    if let Data::Playlist(playlist_data) = data {
+        return Ok(convert_playlist(&playlist_data, share_link, targets, api_clients).await);
+    }
 
-    let tidal_result = match supported_apis.get(&LinkType::Tidal.to_string()) {
-        Some(client) => {
-            let tidal_link = client.data_to_link(&data, &share_link.country_code).await;
-            let image_link = client
-                .get_artwork(&data, &share_link.country_code)
-                .await
-                .unwrap_or(String::new());
-            if tidal_link.is_ok() {
-                let tidal_link = tidal_link.unwrap();
-                let tidal_data = client.link_to_data(&tidal_link).await?;
-                Some(Link::new(&tidal_link, &tidal_data, &image_link))
-            } else {
-                None
+    // every streaming service is queried concurrently, so a slow one only delays its own result
+    let conversions = targets.iter().map(|provider| {
+        convert_with_timeout(*provider, &data, &share_link.country_code, api_clients)
+    });
+
+    let mut converted: Vec<ShareLink> = Vec::new();
+    let mut results: Vec<Link> = Vec::new();
+    let mut errors: Vec<ProviderError> = Vec::new();
+    for result in join_all(conversions).await {
+        match result {
+            Ok((share_link, link)) => {
+                converted.push(share_link);
+                results.push(link);
             }
+            Err(err) => errors.push(err),
         }
-        None => None,
+    }
+
+    let results = ConversionResults { results, errors };
+    // temporary failures would otherwise be served from the cache until the entry expires, under
+    // every converted link. That includes items that were not found, since streaming services
+    // also report exceeded quotas or regional hiccups that way. Results for only some of the
+    // streaming services are not cached, since they could not answer later conversions to the
+    // others.
+    let is_complete = targets.len() == api_clients.providers.iter().count();
+    if is_complete
+        && results
+            .errors
+            .iter()
+            .all(|err| err.reason == ErrorReason::Unsupported)
+    {
+        api_clients.cache.insert(share_link, &converted, &results);
+    }
+
+    Ok(serde_json::to_string(&results).expect("Conversion result should always be valid."))
+}
+
+/// Converts the data to a link of the given provider, unless its circuit breaker is open.
+async fn convert_with_timeout(
+    provider: &dyn MusicProvider,
+    data: &Data,
+    country_code: &CountryCode,
+    api_clients: &ApiClients,
+) -> Result<(ShareLink, Link), ProviderError> {
+    let info = provider.info();
+    let link_type = info.link_type;
+    if !info.converts_to.contains(&share_object(data)) {
+        return Err(ProviderError::new(link_type, &ApiError::UnsupportedFeature));
+    }
+    let breaker = &api_clients.breakers[&link_type];
+    if !breaker.allow_request() {
+        return Err(ProviderError::unavailable(link_type));
+    }
+
+    let result = match tokio::time::timeout(
+        api_clients.timeout,
+        convert_to_client(provider, data, country_code, api_clients),
+    )
+    .await
+    {
+        Ok(Ok(converted)) => Ok(converted),
+        Ok(Err(err)) => Err(ProviderError::new(link_type, &err)),
+        Err(_) => Err(ProviderError::timeout(link_type)),
     };
 
-    let deezer_result = match supported_apis.get(&LinkType::Deezer.to_string()) {
-        Some(client) => {
-            let deezer_link = client.data_to_link(&data, &share_link.country_code).await;
-            let image_link = client
-                .get_artwork(&data, &share_link.country_code)
-                .await
-                .unwrap_or(String::new());
-
-            if deezer_link.is_ok() {
-                let deezer_link = deezer_link.unwrap();
-                let deezer_data = client.link_to_data(&deezer_link).await.unwrap();
-                Some(Link::new(&deezer_link, &deezer_data, &image_link))
-            } else {
-                None
+    // not finding an item or not supporting it means that the streaming service answered
+    let failed = result
+        .as_ref()
+        .is_err_and(|err| !matches!(err.reason, ErrorReason::NotFound | ErrorReason::Unsupported));
+    if failed {
+        breaker.record_failure();
+    } else {
+        breaker.record_success();
+    }
+    result
+}
+
+async fn convert_to_client(
+    provider: &dyn MusicProvider,
+    data: &Data,
+    country_code: &CountryCode,
+    api_clients: &ApiClients,
+) -> Result<(ShareLink, Link), ApiError> {
+    let (found, artwork) = tokio::join!(
+        find_link(provider, data, country_code, &api_clients.store),
+        provider.get_artwork(data, country_code)
+    );
+    let mut found = found?;
+    let target_data = match provider.link_to_data(&found.link).await {
+        Ok(target_data) => target_data,
+        // a stored ID stops working once the streaming service removes the item or changes its
+        // ID, so the mapping is dropped and the item is looked up again
+        Err(err) if found.stored && ErrorReason::from(&err) == ErrorReason::NotFound => {
+            if let Some(identifier) = identifier(data) {
+                api_clients
+                    .store
+                    .remove_link(identifier, &found.link)
+                    .await
+                    .ok();
             }
+            found = find_link(provider, data, country_code, &api_clients.store).await?;
+            provider.link_to_data(&found.link).await?
         }
-        None => None,
+        Err(err) => return Err(err),
     };
+    let confidence = api_clients
+        .matcher
+        .score_data(data, &target_data)
+        .confidence();
 
-    let apple_music_result = match supported_apis.get(&LinkType::AppleMusic.to_string()) {
-        Some(client) => {
-            let apple_music_link = client.data_to_link(&data, &share_link.country_code).await?;
-            let apple_music_data = client.link_to_data(&apple_music_link).await?;
-            Some(Link::new(&apple_music_link, &apple_music_data, ""))
-        }
-        None => None,
+    let converted = Link::new(
+        &found.link,
+        &target_data,
+        &artwork.unwrap_or_default(),
+        found.matched_by,
+        confidence,
+    );
+    Ok((found.link, converted))
+}
+
+/// A link to the data on another streaming service.
+struct FoundLink {
+    link: ShareLink,
+    matched_by: MatchMethod,
+    /// Whether the link was taken from the store instead of asking the streaming service.
+    stored: bool,
+}
+
+/// Looks up the data on the provider by its ISRC or UPC and falls back to searching for its
+/// metadata.
+async fn find_link(
+    provider: &dyn MusicProvider,
+    data: &Data,
+    country_code: &CountryCode,
+    store: &MappingStore,
+) -> Result<FoundLink, ApiError> {
+    match lookup_link(provider, data, country_code, store).await {
+        Ok(found) => Ok(found),
+        Err(ApiError::UnsuccessfulConversion) => Ok(FoundLink {
+            link: provider.search_data_to_link(data, country_code).await?,
+            matched_by: MatchMethod::MetadataSearch,
+            stored: false,
+        }),
+        Err(err) => Err(err),
+    }
+}
+
+/// Looks up the data on the provider by its ISRC or UPC. Links that have been looked up before are
+/// taken from the store instead of asking the provider again. The store only saves requests, so
+/// errors reading from or writing to it are ignored.
+async fn lookup_link(
+    provider: &dyn MusicProvider,
+    data: &Data,
+    country_code: &CountryCode,
+    store: &MappingStore,
+) -> Result<FoundLink, ApiError> {
+    let found = |link, stored| FoundLink {
+        link,
+        matched_by: MatchMethod::lookup(data),
+        stored,
     };
+    let Some(identifier) = identifier(data) else {
+        let link = provider.data_to_link(data, country_code).await?;
+        return Ok(found(link, false));
+    };
+    let link_type = provider.info().link_type;
+    if let Ok(Some(link)) = store
+        .get_link(share_object(data), identifier, link_type, country_code)
+        .await
+    {
+        return Ok(found(link, true));
+    }
 
-    let mut results: Vec<Link> = Vec::new();
+    let link = provider.data_to_link(data, country_code).await?;
+    store.insert_link(identifier, &link).await.ok();
+    Ok(found(link, false))
+}
 
-    if spotify_result.is_some() {
-        results.push(spotify_result.unwrap());
+fn share_object(data: &Data) -> ShareObject {
+    match data {
+        Data::Song(_) => ShareObject::Song,
+        Data::Album(_) => ShareObject::Album,
+        Data::Artist(_) => ShareObject::Artist,
+        Data::Playlist(_) => ShareObject::Playlist,
     }
-    if tidal_result.is_some() {
-        results.push(tidal_result.unwrap());
+}
+
+/// The ISRC of a song or the UPC of an album, if the provider returned one.
+fn identifier(data: &Data) -> Option<&str> {
+    let identifier = match data {
+        Data::Song(song_data) => &song_data.isrc,
+        Data::Album(album_data) => &album_data.upc,
+        Data::Artist(_) | Data::Playlist(_) => return None,
+    };
+    (!identifier.is_empty()).then_some(identifier.as_str())
+}
+
+async fn convert_playlist(
+    playlist_data: &PlaylistData,
+    share_link: &ShareLink,
+    targets: &[&dyn MusicProvider],
+    api_clients: &ApiClients,
+) -> String {
+    let conversions = targets.iter().map(|provider| {
+        convert_playlist_to_client(
+            *provider,
+            playlist_data,
+            &share_link.country_code,
+            api_clients,
+        )
+    });
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for result in join_all(conversions).await {
+        match result {
+            Ok(playlist_link) => results.push(playlist_link),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    let conversion = PlaylistConversionResults {
+        results,
+        errors,
+        truncated: playlist_data.truncated,
+    };
+    serde_json::to_string(&conversion).expect("Conversion result should always be valid.")
+}
+
+// the number of tracks of a playlist that are looked up at the same time
+const MAX_CONCURRENT_PLAYLIST_LOOKUPS: usize = 5;
+
+/// Looks up the tracks of the playlist, a few at a time and no faster than the provider allows.
+/// Since a playlist can be long, the timeout applies to each lookup instead of the whole
+/// playlist. Tracks that are not found are listed as unmatched, while any other error fails the
+/// whole playlist, since it would most likely repeat for the remaining tracks.
+async fn convert_playlist_to_client(
+    provider: &dyn MusicProvider,
+    playlist_data: &PlaylistData,
+    country_code: &CountryCode,
+    api_clients: &ApiClients,
+) -> Result<PlaylistLink, ProviderError> {
+    let info = provider.info();
+    let link_type = info.link_type;
+    if !info.converts_to.contains(&ShareObject::Playlist) {
+        return Err(ProviderError::new(link_type, &ApiError::UnsupportedFeature));
     }
-    if deezer_result.is_some() {
-        results.push(deezer_result.unwrap());
+    // the many lookups of a playlist are not counted towards the circuit breaker, but a streaming
+    // service that is known to be failing is skipped
+    if !api_clients.breakers[&link_type].is_available() {
+        return Err(ProviderError::unavailable(link_type));
     }
-    if apple_music_result.is_some() {
-        results.push(apple_music_result.unwrap());
+
+    // the lookups take turns, so that they stay spread out even after a slow one
+    let interval = provider.playlist_lookup_interval();
+    let pace = (!interval.is_zero()).then(|| {
+        let mut pace = tokio::time::interval(interval);
+        pace.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Mutex::new(pace)
+    });
+    let pace = &pace;
+    let lookups = stream::iter(playlist_data.songs.iter().cloned())
+        .map(|song_data| async move {
+            // tracks without an ISRC, e.g. local files, can not be looked up
+            if song_data.isrc.is_empty() {
+                return (song_data, Ok(None));
+            }
+            let data = Data::Song(song_data.clone());
+            if let Some(pace) = pace {
+                pace.lock().await.tick().await;
+            }
+            let track_link = match tokio::time::timeout(
+                api_clients.timeout,
+                lookup_link(provider, &data, country_code, &api_clients.store),
+            )
+            .await
+            {
+                Ok(Ok(found)) => Ok(Some(found.link)),
+                Ok(Err(err)) if ErrorReason::from(&err) == ErrorReason::NotFound => Ok(None),
+                Ok(Err(err)) => Err(ProviderError::new(link_type, &err)),
+                Err(_) => Err(ProviderError::timeout(link_type)),
+            };
+            (song_data, track_link)
+        })
+        .buffered(MAX_CONCURRENT_PLAYLIST_LOOKUPS);
+    let mut lookups = pin!(lookups);
+
+    let mut tracks = Vec::new();
+    let mut unmatched = Vec::new();
+    while let Some((song_data, track_link)) = lookups.next().await {
+        match track_link? {
+            // the tracks are not fetched from the provider again, so an ISRC hit is taken as
+            // certain
+            Some(track_link) => tracks.push(Link::new(
+                &track_link,
+                &Data::Song(song_data),
+                "",
+                MatchMethod::Isrc,
+                1.0,
+            )),
+            None => unmatched.push(UnmatchedTrack {
+                display_name: song_data.display_name,
+                isrc: song_data.isrc,
+            }),
+        }
     }
 
-    Ok(serde_json::to_string(&ConversionResults { results })
-        .expect("Conversion result should always be valid."))
+    Ok(PlaylistLink {
+        provider: link_type.to_string(),
+        r#type: String::from("Playlist"),
+        display_name: playlist_data.display_name.clone(),
+        tracks,
+        unmatched,
+    })
 }
@@ -0,0 +1,120 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use futures::future::join_all;
+use rust_iso3166::CountryCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{ApiError, provider::MusicProvider},
+    share_link::{ShareLink, ShareObject},
+    shared_item::Data,
+};
+
+use super::{
+    ApiClients, ConversionError, ProviderError, find_link, parse_share_link, share_object,
+};
+
+/// The maximum number of countries that can be checked at once. Some streaming services need a
+/// request per country.
+pub const MAX_COUNTRIES: usize = 25;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AvailabilityResults {
+    pub results: Vec<Availability>,
+    /// The streaming services for which the availability could not be checked.
+    #[serde(default)]
+    pub errors: Vec<ProviderError>,
+}
+
+/// Where the song or album can be played on a streaming service.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Availability {
+    pub provider: String,
+    pub url: String,
+    /// Whether the item can be played, by ISO 3166-1 alpha-2 country code.
+    pub countries: BTreeMap<String, bool>,
+}
+
+/// Checks in which of the countries the song or album of the link can be played on each
+/// streaming service. The item is looked up on the other streaming services in the country of
+/// the link.
+pub async fn check_availability(
+    url: &str,
+    countries: &[CountryCode],
+    api_clients: Arc<ApiClients>,
+) -> Result<String, ConversionError> {
+    let (share_link, source) = parse_share_link(url, None, &api_clients).await?;
+    // fetching playlists or artists is expensive, so they are rejected before the lookup
+    if !matches!(share_link.share_obj, ShareObject::Song | ShareObject::Album) {
+        return Err(ConversionError::ApiClient(ApiError::UnsupportedFeature));
+    }
+    let data = source.link_to_data(&share_link).await?;
+
+    let checks = api_clients.providers.iter().map(|provider| {
+        check_provider(
+            provider.as_ref(),
+            &share_link,
+            &data,
+            countries,
+            &api_clients,
+        )
+    });
+
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for result in join_all(checks).await {
+        match result {
+            Ok(availability) => results.push(availability),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    Ok(
+        serde_json::to_string(&AvailabilityResults { results, errors })
+            .expect("Availability result should always be valid."),
+    )
+}
+
+async fn check_provider(
+    provider: &dyn MusicProvider,
+    share_link: &ShareLink,
+    data: &Data,
+    countries: &[CountryCode],
+    api_clients: &ApiClients,
+) -> Result<Availability, ProviderError> {
+    let info = provider.info();
+    let link_type = info.link_type;
+    if !info.converts_to.contains(&share_object(data)) {
+        return Err(ProviderError::new(link_type, &ApiError::UnsupportedFeature));
+    }
+    // like playlists, availability checks are not counted towards the circuit breaker
+    if !api_clients.breakers[&link_type].is_available() {
+        return Err(ProviderError::unavailable(link_type));
+    }
+
+    let check = async {
+        let link = if link_type == share_link.link_type {
+            share_link.clone()
+        } else {
+            find_link(provider, data, &share_link.country_code, &api_clients.store)
+                .await?
+                .link
+        };
+        let available = provider.available_countries(&link, countries).await?;
+        Ok::<_, ApiError>((link, available))
+    };
+    let (link, available) = match tokio::time::timeout(api_clients.timeout, check).await {
+        Ok(Ok(checked)) => checked,
+        Ok(Err(err)) => return Err(ProviderError::new(link_type, &err)),
+        Err(_) => return Err(ProviderError::timeout(link_type)),
+    };
+
+    Ok(Availability {
+        provider: link_type.to_string(),
+        url: link.to_url(),
+        countries: countries
+            .iter()
+            .map(|country| (country.alpha2.to_owned(), available.contains(country)))
+            .collect(),
+    })
+}
@@ -0,0 +1,88 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+
+use crate::{
+    config::CacheConfig,
+    share_link::{LinkType, ShareLink, ShareObject},
+};
+
+use super::ConversionResults;
+
+/// Identifies a share link independent of the URL it was parsed from, e.g. tracking parameters
+/// or a different domain of the same streaming service.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    link_type: LinkType,
+    share_obj: ShareObject,
+    id: String,
+    country: &'static str,
+}
+
+impl From<&ShareLink> for CacheKey {
+    fn from(link: &ShareLink) -> Self {
+        Self {
+            link_type: link.link_type,
+            share_obj: link.share_obj,
+            id: link.id.clone(),
+            country: link.country_code.alpha2,
+        }
+    }
+}
+
+struct CacheEntry {
+    results: ConversionResults,
+    inserted: Instant,
+}
+
+/// A bounded cache of conversion results. Entries are evicted when they are the least recently
+/// used one and the cache is full or when they are older than the TTL.
+pub struct ConversionCache {
+    entries: Option<Mutex<LruCache<CacheKey, CacheEntry>>>,
+    ttl: Duration,
+}
+
+impl ConversionCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self {
+            entries: NonZeroUsize::new(config.capacity)
+                .map(|capacity| Mutex::new(LruCache::new(capacity))),
+            ttl: Duration::from_secs(config.ttl),
+        }
+    }
+
+    pub fn get(&self, link: &ShareLink) -> Option<ConversionResults> {
+        let mut entries = self.entries.as_ref()?.lock().unwrap();
+        let key = CacheKey::from(link);
+
+        let entry = entries.get(&key)?;
+        if entry.inserted.elapsed() > self.ttl {
+            entries.pop(&key);
+            return None;
+        }
+        Some(entry.results.clone())
+    }
+
+    /// Caches the results under the converted link and under every link it was converted to, so
+    /// that converting any of them again does not need to query the streaming services.
+    pub fn insert(&self, link: &ShareLink, converted: &[ShareLink], results: &ConversionResults) {
+        let Some(entries) = self.entries.as_ref() else {
+            return;
+        };
+        let mut entries = entries.lock().unwrap();
+
+        for link in std::iter::once(link).chain(converted) {
+            entries.put(
+                CacheKey::from(link),
+                CacheEntry {
+                    results: results.clone(),
+                    inserted: Instant::now(),
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// Requests are let through. Counts the failures in a row.
+    Closed { failures: u32 },
+    /// Requests are skipped until the cooldown is over.
+    Open { until: Instant },
+    /// A single probe request is let through to check whether the provider recovered. If its
+    /// outcome is never recorded, e.g. because the request was dropped, another probe is let
+    /// through after the cooldown.
+    HalfOpen { since: Instant },
+}
+
+/// Stops requests to a streaming service after repeated failures, so that conversions do not
+/// wait for a service that is down. After a cooldown a single request is let through as a probe.
+/// If it succeeds the service is used again, otherwise the cooldown starts over.
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            state: Mutex::new(State::Closed { failures: 0 }),
+            failure_threshold,
+            cooldown,
+        }
+    }
+
+    /// Whether a request may be sent. Once the cooldown is over, this lets exactly one probe
+    /// through, whose outcome has to be recorded.
+    pub fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            State::HalfOpen { since } if since.elapsed() >= self.cooldown => {
+                *state = State::HalfOpen {
+                    since: Instant::now(),
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = match *state {
+            State::Closed { failures } if failures + 1 < self.failure_threshold => State::Closed {
+                failures: failures + 1,
+            },
+            _ => State::Open {
+                until: Instant::now() + self.cooldown,
+            },
+        };
+    }
+
+    /// Whether the streaming service is considered healthy. Does not count as a probe.
+    pub fn is_available(&self) -> bool {
+        match *self.state.lock().unwrap() {
+            State::Closed { .. } => true,
+            State::Open { until } => Instant::now() >= until,
+            State::HalfOpen { .. } => false,
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::{collections::HashMap, future::Future, hash::Hash, sync::Arc, sync::Mutex};
+
+use tokio::sync::OnceCell;
+
+/// Deduplicates concurrent calls with the same key. While a call is in flight, further calls
+/// with its key wait for it and receive its result instead of doing the work again.
+pub struct SingleFlight<K, V> {
+    calls: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> SingleFlight<K, V> {
+    pub fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `work` unless a call with the same key is already running. Errors are not shared:
+    /// if the running call fails, one of the waiting calls runs `work` itself.
+    pub async fn run<F, Fut, E>(&self, key: K, work: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let call = self
+            .calls
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let result = call.get_or_try_init(work).await.cloned();
+
+        // the first successful call removes the entry, so that later calls start a new one. If
+        // the calls failed, the last one to give up removes it.
+        let mut calls = self.calls.lock().unwrap();
+        let is_running = calls
+            .get(&key)
+            .is_some_and(|running| Arc::ptr_eq(running, &call));
+        if is_running && (call.initialized() || Arc::strong_count(&call) == 2) {
+            calls.remove(&key);
+        }
+        result
+    }
+
+    /// The number of calls that are currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
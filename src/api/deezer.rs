@@ -1,25 +1,44 @@
-use reqwest::Client;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{Client, Url};
 use rust_iso3166::CountryCode;
 use serde::Deserialize;
 
 use crate::{
-    share_link::{LinkType, ShareLink, ShareObject},
-    shared_item::{AlbumData, ArtistData, SongData},
+    share_link::{
+        LinkFormat, LinkType, PathParts, ShareLink, ShareLinkError, ShareObject, country_of_locale,
+        path_segments, share_object,
+    },
+    shared_item::{AlbumData, ArtistData, Data, PlaylistData, SongData, matching::Matcher},
 };
 
-use super::ApiError;
+use super::{
+    ApiError,
+    provider::{MusicProvider, ProviderInfo},
+    request::RequestExt,
+};
 
 #[derive(Clone)]
 pub struct DeezerApi {
     client: Client,
+    matcher: Matcher,
 }
 
 impl DeezerApi {
     const BASE_URL: &'static str = "https://api.deezer.com";
+    const MAX_DISCOGRAPHY_SIZE: usize = 20;
+    const MAX_ARTIST_CANDIDATES: usize = 3;
+    const MAX_PLAYLIST_SIZE: usize = 100;
+    /// Deezer allows 50 requests per 5 seconds, the tracks of a playlist are fetched and looked
+    /// up at 8 per second to leave room for other requests.
+    const PLAYLIST_TRACK_INTERVAL: Duration = Duration::from_millis(125);
+    const MAX_SEARCH_CANDIDATES: usize = 5;
 
-    pub fn new(client: &Client) -> Self {
+    pub fn new(client: &Client, matcher: &Matcher) -> Self {
         Self {
             client: client.clone(),
+            matcher: matcher.clone(),
         }
     }
 
@@ -44,9 +63,7 @@ impl DeezerApi {
         let response = self
             .client
             .get(format!("{}/track/{}", Self::BASE_URL, song_link.id))
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let song_info: SongQuery = serde_json::from_str(&response)?;
@@ -74,25 +91,211 @@ impl DeezerApi {
         struct AlbumQuery {
             title: String,
             upc: String,
+            artist: ArtistInfo,
+        }
+        #[derive(Deserialize, Debug)]
+        struct ArtistInfo {
+            name: String,
         }
 
         let response = self
             .client
             .get(format!("{}/album/{}", Self::BASE_URL, album_link.id))
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
         let album_info: AlbumQuery = serde_json::from_str(&response)?;
 
-        Ok(AlbumData::with_limited_info(
+        Ok(AlbumData::new(
             &album_info.title,
             &album_info.upc,
+            Vec::new(),
+            vec![ArtistData::without_albums(&album_info.artist.name)],
         ))
     }
 
-    pub async fn get_artist_data(&self, _artist_link: ShareLink) -> Result<ArtistData, ApiError> {
-        todo!()
+    pub async fn get_artist_data(&self, artist_link: &ShareLink) -> Result<ArtistData, ApiError> {
+        #[derive(Deserialize)]
+        struct ArtistQuery {
+            name: String,
+        }
+        #[derive(Deserialize)]
+        struct ArtistAlbums {
+            data: Vec<AlbumInfo>,
+        }
+        #[derive(Deserialize)]
+        struct AlbumInfo {
+            title: String,
+        }
+
+        if artist_link.link_type != LinkType::Deezer || artist_link.share_obj != ShareObject::Artist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/artist/{}", Self::BASE_URL, artist_link.id))
+            .send_with_retry()
+            .await?;
+        let artist_info: ArtistQuery = serde_json::from_str(&response)?;
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/artist/{}/albums?limit={}",
+                Self::BASE_URL,
+                artist_link.id,
+                Self::MAX_DISCOGRAPHY_SIZE
+            ))
+            .send_with_retry()
+            .await?;
+        let artist_albums: ArtistAlbums = serde_json::from_str(&response)?;
+
+        // The album listing of an artist does not contain the UPC, but the titles are enough to
+        // compare discographies. Fetching every album would exceed the request quota of Deezer.
+        let albums = artist_albums
+            .data
+            .iter()
+            .map(|album| {
+                AlbumData::new(
+                    &album.title,
+                    "",
+                    Vec::new(),
+                    vec![ArtistData::without_albums(&artist_info.name)],
+                )
+            })
+            .collect();
+
+        Ok(ArtistData::new(&artist_info.name, albums))
+    }
+
+    pub async fn get_artist_link(
+        &self,
+        artist_data: &ArtistData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize)]
+        struct ArtistSearch {
+            data: Vec<ArtistInfo>,
+        }
+        #[derive(Deserialize)]
+        struct ArtistInfo {
+            id: u64,
+        }
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search/artist?q={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&artist_data.display_name),
+                Self::MAX_ARTIST_CANDIDATES
+            ))
+            .send_with_retry()
+            .await?;
+        let search: ArtistSearch = serde_json::from_str(&response)?;
+
+        // Artist names are not unique, so every candidate has to be confirmed by comparing the
+        // discographies.
+        let mut candidate_error = None;
+        for artist in search.data {
+            let candidate = ShareLink::new(
+                LinkType::Deezer,
+                ShareObject::Artist,
+                &artist.id.to_string(),
+                country_code,
+            );
+            let candidate_data = match self.get_artist_data(&candidate).await {
+                Ok(candidate_data) => candidate_data,
+                // a candidate that can not be fetched must not prevent checking the others
+                Err(err) => {
+                    candidate_error = Some(err);
+                    continue;
+                }
+            };
+            if self.matcher.artists_match(artist_data, &candidate_data) {
+                return Ok(candidate);
+            }
+        }
+
+        // if a candidate failed, it might have been the artist
+        Err(candidate_error.unwrap_or(ApiError::UnsuccessfulConversion))
+    }
+
+    pub async fn get_playlist_data(
+        &self,
+        playlist_link: &ShareLink,
+    ) -> Result<PlaylistData, ApiError> {
+        #[derive(Deserialize)]
+        struct PlaylistQuery {
+            title: String,
+            nb_tracks: usize,
+            tracks: Tracks,
+        }
+        #[derive(Deserialize)]
+        struct Tracks {
+            data: Vec<TrackInfo>,
+        }
+        #[derive(Deserialize)]
+        struct TrackInfo {
+            id: u64,
+        }
+        #[derive(Deserialize)]
+        struct SongQuery {
+            title: String,
+            isrc: String,
+            duration: u64,
+            artist: ArtistInfo,
+        }
+        #[derive(Deserialize)]
+        struct ArtistInfo {
+            name: String,
+        }
+
+        if playlist_link.link_type != LinkType::Deezer
+            || playlist_link.share_obj != ShareObject::Playlist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/playlist/{}", Self::BASE_URL, playlist_link.id))
+            .send_with_retry()
+            .await?;
+        let playlist_info: PlaylistQuery = serde_json::from_str(&response)?;
+
+        // The tracks of a playlist do not contain the ISRC, so each track has to be fetched on
+        // its own. The fetches are spaced out to stay below the request quota of Deezer.
+        let mut songs = Vec::new();
+        let mut pace = tokio::time::interval(Self::PLAYLIST_TRACK_INTERVAL);
+        for track in playlist_info
+            .tracks
+            .data
+            .iter()
+            .take(Self::MAX_PLAYLIST_SIZE)
+        {
+            pace.tick().await;
+            let response = self
+                .client
+                .get(format!("{}/track/{}", Self::BASE_URL, track.id))
+                .send_with_retry()
+                .await?;
+            let song_info: SongQuery = serde_json::from_str(&response)?;
+
+            songs.push(SongData::new(
+                &song_info.title,
+                &song_info.isrc,
+                song_info.duration,
+                Vec::new(),
+                vec![ArtistData::without_albums(&song_info.artist.name)],
+            ));
+        }
+
+        let mut playlist = PlaylistData::new(&playlist_info.title, songs);
+        playlist.truncated = playlist_info.nb_tracks > Self::MAX_PLAYLIST_SIZE
+            || playlist_info.tracks.data.len() > Self::MAX_PLAYLIST_SIZE;
+        Ok(playlist)
     }
 
     pub async fn get_song_link(
@@ -108,9 +311,7 @@ impl DeezerApi {
         let response = self
             .client
             .get(format!("{}/track/isrc:{}", Self::BASE_URL, song_data.isrc))
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
         let song_info: SongQuery = serde_json::from_str(&response)?;
         Ok(ShareLink::new(
@@ -134,9 +335,7 @@ impl DeezerApi {
         let response = self
             .client
             .get(format!("{}/album/upc:{}", Self::BASE_URL, album_data.upc))
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
         let album_info: AlbumQuery = serde_json::from_str(&response)?;
         Ok(ShareLink::new(
@@ -147,6 +346,130 @@ impl DeezerApi {
         ))
     }
 
+    /// Searches for a song by its title and artist. This is the fallback for songs, which can
+    /// not be found by their ISRC, e.g. because of regional re-releases.
+    pub async fn search_song_link(
+        &self,
+        song_data: &SongData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize)]
+        struct TrackSearch {
+            data: Vec<TrackInfo>,
+        }
+        #[derive(Deserialize)]
+        struct TrackInfo {
+            id: u64,
+            title: String,
+            duration: u64,
+            artist: ArtistInfo,
+        }
+        #[derive(Deserialize)]
+        struct ArtistInfo {
+            name: String,
+        }
+
+        let artist = match song_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("artist:\"{}\" track:\"{}\"", artist, song_data.norm_name());
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search/track?q={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .send_with_retry()
+            .await?;
+        let search: TrackSearch = serde_json::from_str(&response)?;
+
+        for track in search.data {
+            let candidate = SongData::new(
+                &track.title,
+                "",
+                track.duration,
+                Vec::new(),
+                vec![ArtistData::without_albums(&track.artist.name)],
+            );
+
+            if self.matcher.songs_match(song_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::Deezer,
+                    ShareObject::Song,
+                    &track.id.to_string(),
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
+    /// Searches for an album by its title and artist. This is the fallback for albums, which can
+    /// not be found by their UPC.
+    pub async fn search_album_link(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize)]
+        struct AlbumSearch {
+            data: Vec<AlbumInfo>,
+        }
+        #[derive(Deserialize)]
+        struct AlbumInfo {
+            id: u64,
+            title: String,
+            artist: ArtistInfo,
+        }
+        #[derive(Deserialize)]
+        struct ArtistInfo {
+            name: String,
+        }
+
+        let artist = match album_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("artist:\"{}\" album:\"{}\"", artist, album_data.norm_name());
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search/album?q={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .send_with_retry()
+            .await?;
+        let search: AlbumSearch = serde_json::from_str(&response)?;
+
+        for album in search.data {
+            let candidate = AlbumData::new(
+                &album.title,
+                "",
+                Vec::new(),
+                vec![ArtistData::without_albums(&album.artist.name)],
+            );
+
+            if self.matcher.albums_match(album_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::Deezer,
+                    ShareObject::Album,
+                    &album.id.to_string(),
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
     pub async fn get_cover_art(&self, album_data: &AlbumData) -> Result<String, ApiError> {
         #[derive(Deserialize)]
         struct AlbumQuery {
@@ -158,9 +481,7 @@ impl DeezerApi {
         let response = self
             .client
             .get(format!("{}/album/upc:{}", Self::BASE_URL, album_data.upc))
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let album_info: AlbumQuery = serde_json::from_str(&response)?;
@@ -176,4 +497,179 @@ impl DeezerApi {
         }
         return Ok(album_info.cover);
     }
+
+    /// The countries out of `countries` in which the song is available according to its
+    /// `available_countries`. Albums do not list the countries they are available in.
+    pub async fn get_available_countries(
+        &self,
+        song_link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        #[derive(Deserialize)]
+        struct SongQuery {
+            available_countries: Vec<String>,
+        }
+
+        if song_link.share_obj != ShareObject::Song {
+            return Err(ApiError::UnsupportedFeature);
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/track/{}", Self::BASE_URL, song_link.id))
+            .send_with_retry()
+            .await?;
+
+        let song_info: SongQuery = serde_json::from_str(&response)?;
+        Ok(countries
+            .iter()
+            .filter(|country| {
+                song_info
+                    .available_countries
+                    .iter()
+                    .any(|available| available == country.alpha2)
+            })
+            .copied()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MusicProvider for DeezerApi {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            link_type: LinkType::Deezer,
+            url: "https://www.deezer.com",
+            asset_name: "deezer",
+            parses: &ShareObject::ALL,
+            converts_to: &ShareObject::ALL,
+        }
+    }
+
+    fn playlist_lookup_interval(&self) -> Duration {
+        Self::PLAYLIST_TRACK_INTERVAL
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Song => Ok(Data::Song(self.get_song_data(link).await?)),
+            ShareObject::Album => Ok(Data::Album(self.get_album_data(link).await?)),
+            ShareObject::Artist => Ok(Data::Artist(self.get_artist_data(link).await?)),
+            ShareObject::Playlist => Ok(Data::Playlist(self.get_playlist_data(link).await?)),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.get_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.get_album_link(album_data, country_code).await,
+            Data::Artist(artist_data) => self.get_artist_link(artist_data, country_code).await,
+            // playlists are user specific and can only be converted track by track
+            Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn search_data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.search_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.search_album_link(album_data, country_code).await,
+            // artists are already looked up by searching for them
+            Data::Artist(_) | Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn get_artwork(
+        &self,
+        data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        let album_data = match data {
+            Data::Song(song_data) => song_data
+                .albums
+                .first()
+                .ok_or(ApiError::IncorrectAttributes)?,
+            Data::Album(album_data) => album_data,
+            Data::Artist(_) | Data::Playlist(_) => return Err(ApiError::UnsupportedFeature),
+        };
+        self.get_cover_art(album_data).await
+    }
+
+    async fn available_countries(
+        &self,
+        link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        self.get_available_countries(link, countries).await
+    }
+}
+
+pub struct DeezerLinks;
+
+impl LinkFormat for DeezerLinks {
+    fn link_type(&self) -> LinkType {
+        LinkType::Deezer
+    }
+
+    fn name(&self) -> &'static str {
+        "Deezer"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["deezer.com", "m.deezer.com"]
+    }
+
+    fn app_schemes(&self) -> &'static [&'static str] {
+        &["deezer"]
+    }
+
+    /// `deezer://www.deezer.com/{kind}/{id}`, whose path is the one of the web link.
+    fn app_link_segments<'a>(&self, url: &'a Url) -> Result<Vec<&'a str>, ShareLinkError> {
+        let host = url.host_str().map(str::to_ascii_lowercase);
+        match host.as_deref() {
+            Some("deezer.com" | "www.deezer.com") => Ok(path_segments(url)),
+            _ => Err(ShareLinkError::MalformedOrInvalidLink),
+        }
+    }
+
+    /// `/[{locale}/]{kind}/{id}`, where the locale is a language like `en`, a country like `de` or
+    /// both like `en-gb`.
+    fn parse_path(&self, _url: &Url, segments: &[&str]) -> Result<PathParts, ShareLinkError> {
+        match segments {
+            [kind, id] => Ok((None, share_object(kind)?, id.to_string())),
+            [locale, kind, id] if matches!(locale.len(), 2 | 5) => Ok((
+                country_of_locale(locale),
+                share_object(kind)?,
+                id.to_string(),
+            )),
+            _ => Err(ShareLinkError::MalformedOrInvalidLink),
+        }
+    }
+
+    fn to_url(&self, link: &ShareLink) -> String {
+        format!(
+            "https://www.deezer.com/{}/{}",
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn to_app_uri(&self, link: &ShareLink) -> String {
+        format!(
+            "deezer://www.deezer.com/{}/{}",
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn android_package(&self) -> &'static str {
+        "deezer.android.app"
+    }
 }
@@ -0,0 +1,107 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use rust_iso3166::CountryCode;
+
+use crate::{
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::Data,
+};
+
+use super::ApiError;
+
+/// Static information about a streaming service.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProviderInfo {
+    pub link_type: LinkType,
+    /// The website of the streaming service.
+    pub url: &'static str,
+    /// The prefix of the logo and icon files in the `public` directory, e.g. `spotify` for
+    /// `spotify_logo.png` and `spotify_icon.png`.
+    pub asset_name: &'static str,
+    /// The kinds of share links that can be converted from the streaming service.
+    pub parses: &'static [ShareObject],
+    /// The kinds of items that can be converted to the streaming service. Playlists are
+    /// converted track by track.
+    pub converts_to: &'static [ShareObject],
+}
+
+/// A streaming service links can be converted from and to. Share links are parsed into a
+/// [`ShareLink`] by the [`LinkFormat`](crate::share_link::LinkFormat) of the streaming service,
+/// and the providers then fetch its data or look up the link to data fetched from another
+/// provider.
+#[async_trait]
+pub trait MusicProvider: Send + Sync {
+    fn info(&self) -> ProviderInfo;
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError>;
+
+    /// Looks up the data by its ISRC or UPC. Artists have neither and are looked up by their
+    /// name and discography.
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError>;
+
+    /// Looks up the data by searching for its metadata instead of its ISRC or UPC. The candidates
+    /// are only accepted, if their metadata agrees with the data.
+    async fn search_data_to_link(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        Err(ApiError::UnsupportedFeature)
+    }
+
+    async fn get_artwork(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError>;
+
+    /// The minimum time between the lookups of the tracks of a playlist, for streaming services
+    /// whose request quota would otherwise be exceeded by long playlists.
+    fn playlist_lookup_interval(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// The countries out of `countries` in which the song or album of the link can be played.
+    async fn available_countries(
+        &self,
+        _link: &ShareLink,
+        _countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        Err(ApiError::UnsupportedFeature)
+    }
+}
+
+/// The configured streaming services, in the order in which they appear in conversion results.
+#[derive(Clone, Default)]
+pub struct ProviderRegistry {
+    providers: Vec<Arc<dyn MusicProvider>>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the provider. A provider that is registered again replaces the previous one.
+    pub fn register(&mut self, provider: impl MusicProvider + 'static) {
+        let link_type = provider.info().link_type;
+        self.providers
+            .retain(|registered| registered.info().link_type != link_type);
+        self.providers.push(Arc::new(provider));
+    }
+
+    pub fn get(&self, link_type: LinkType) -> Option<&Arc<dyn MusicProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.info().link_type == link_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn MusicProvider>> {
+        self.providers.iter()
+    }
+}
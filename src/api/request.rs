@@ -0,0 +1,108 @@
+use std::{future::Future, time::Duration};
+
+use reqwest::{RequestBuilder, Response, StatusCode, header::RETRY_AFTER};
+use serde::Deserialize;
+
+use super::ApiError;
+
+// A conversion is cut off by the provider timeout anyway, so there is no point in retrying more
+// often or waiting longer than that.
+const MAX_ATTEMPTS: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(2);
+
+pub trait RequestExt {
+    /// Sends the request and returns the body of the successful response. Timeouts, server
+    /// errors and rate limits with a short `Retry-After` are retried with exponential backoff.
+    /// Errors reported in the body of a successful response, as Deezer does, are treated the
+    /// same way.
+    fn send_with_retry(self) -> impl Future<Output = Result<String, ApiError>> + Send;
+}
+
+impl RequestExt for RequestBuilder {
+    async fn send_with_retry(self) -> Result<String, ApiError> {
+        let mut attempt = 1;
+        loop {
+            let request = self
+                .try_clone()
+                .expect("Requests to the streaming services never have a streaming body.");
+            let error = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    let body = response.text().await?;
+                    match body_error(&body) {
+                        Some(error) => error,
+                        None => return Ok(body),
+                    }
+                }
+                Ok(response) => status_error(&response),
+                Err(err) => ApiError::RequestError(err),
+            };
+
+            let delay = match retry_delay(&error, attempt) {
+                Some(delay) if attempt < MAX_ATTEMPTS => delay,
+                _ => return Err(error),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn status_error(response: &Response) -> ApiError {
+    match response.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Unauthorized,
+        StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited {
+            retry_after: response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok()),
+        },
+        status => ApiError::UnexpectedStatus(status.as_u16()),
+    }
+}
+
+/// Deezer responds with status 200 and an error object instead of the requested item, e.g.
+/// `{"error":{"type":"Exception","message":"Quota limit exceeded","code":4}}`.
+fn body_error(body: &str) -> Option<ApiError> {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: ServiceError,
+    }
+
+    #[derive(Deserialize)]
+    struct ServiceError {
+        code: u32,
+        #[serde(default)]
+        message: String,
+    }
+
+    let error = serde_json::from_str::<ErrorBody>(body).ok()?.error;
+    // see https://developers.deezer.com/api/errors
+    Some(match error.code {
+        4 => ApiError::RateLimited { retry_after: None },
+        200 | 300 => ApiError::Unauthorized,
+        800 => ApiError::UnsuccessfulConversion,
+        code => ApiError::ServiceError {
+            code,
+            message: error.message,
+        },
+    })
+}
+
+/// How long to wait before the next attempt, or `None` if the error is not transient.
+fn retry_delay(error: &ApiError, attempt: u32) -> Option<Duration> {
+    let backoff = INITIAL_BACKOFF * 2u32.pow(attempt - 1);
+    match error {
+        ApiError::RateLimited {
+            retry_after: Some(seconds),
+        } => {
+            let retry_after = Duration::from_secs(*seconds);
+            (retry_after <= MAX_RETRY_AFTER).then_some(retry_after)
+        }
+        ApiError::RateLimited { retry_after: None } => Some(backoff),
+        ApiError::UnexpectedStatus(status) if *status >= 500 => Some(backoff),
+        ApiError::RequestError(err) if err.is_timeout() || err.is_connect() => Some(backoff),
+        _ => None,
+    }
+}
@@ -1,17 +1,26 @@
 use std::{sync::Arc, time::Duration};
 
-use reqwest::Client;
+use async_trait::async_trait;
+use reqwest::{Client, Url};
 use rust_iso3166::CountryCode;
 use serde::Deserialize;
 use tokio::sync::RwLock;
 
 use crate::{
     config::ClientCredentials,
-    share_link::{LinkType, ShareLink, ShareObject},
-    shared_item::{AlbumData, ArtistData, SongData},
+    share_link::{
+        LinkFormat, LinkType, PathParts, ShareLink, ShareLinkError, ShareObject, country_of_locale,
+        share_object,
+    },
+    shared_item::{AlbumData, ArtistData, Data, PlaylistData, SongData, matching::Matcher},
 };
 
-use super::{ApiError, authorization::AccessToken};
+use super::{
+    ApiError,
+    authorization::AccessToken,
+    provider::{MusicProvider, ProviderInfo},
+    request::RequestExt,
+};
 
 #[derive(Deserialize, Debug, Clone)]
 enum ExternalId {
@@ -36,13 +45,18 @@ pub struct SpotifyApi {
     client: Client,
     creds: ClientCredentials,
     access_token: Arc<RwLock<AccessToken>>,
+    matcher: Matcher,
 }
 
 impl SpotifyApi {
     const BASE_URL: &'static str = "https://api.spotify.com/v1";
     const AUTH_ENDPOINT: &'static str = "https://accounts.spotify.com/api/token";
+    const MAX_DISCOGRAPHY_SIZE: usize = 50;
+    const MAX_ARTIST_CANDIDATES: usize = 3;
+    const MAX_PLAYLIST_SIZE: usize = 100;
+    const MAX_SEARCH_CANDIDATES: usize = 5;
 
-    pub async fn new(client: &Client, credentials: &ClientCredentials) -> Self {
+    pub async fn new(client: &Client, credentials: &ClientCredentials, matcher: &Matcher) -> Self {
         Self {
             client: client.clone(),
             creds: credentials.clone(),
@@ -51,6 +65,7 @@ impl SpotifyApi {
                     .await
                     .unwrap(),
             )),
+            matcher: matcher.clone(),
         }
     }
 
@@ -88,9 +103,7 @@ impl SpotifyApi {
                 &song_link.country_code.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: SongQuery = serde_json::from_str(&response)?;
@@ -128,6 +141,7 @@ impl SpotifyApi {
         struct AlbumQuery {
             name: String,
             external_ids: ExternalId,
+            artists: Vec<Artist>,
         }
 
         let response = self
@@ -139,9 +153,7 @@ impl SpotifyApi {
                 &album_link.country_code.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: AlbumQuery = serde_json::from_str(&response)?;
@@ -151,11 +163,233 @@ impl SpotifyApi {
             _ => return Err(ApiError::IncorrectAttributes),
         };
 
-        Ok(AlbumData::with_limited_info(&result.name, &upc))
+        let artists = result
+            .artists
+            .iter()
+            .map(|artist| ArtistData::without_albums(&artist.name))
+            .collect();
+
+        Ok(AlbumData::new(&result.name, &upc, Vec::new(), artists))
+    }
+
+    pub async fn get_artist_data(&self, artist_link: &ShareLink) -> Result<ArtistData, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct ArtistAlbums {
+            items: Vec<Item>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct Albums {
+            albums: Vec<AlbumInfo>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct AlbumInfo {
+            name: String,
+            external_ids: ExternalId,
+        }
+
+        if artist_link.link_type != LinkType::Spotify
+            || artist_link.share_obj != ShareObject::Artist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let response = self
+            .client
+            .get(format!("{}/artists/{}", Self::BASE_URL, &artist_link.id))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+        let artist: Artist = serde_json::from_str(&response)?;
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/artists/{}/albums?include_groups=album,single&market={}&limit={}",
+                Self::BASE_URL,
+                &artist_link.id,
+                &artist_link.country_code.alpha2,
+                Self::MAX_DISCOGRAPHY_SIZE
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+        let artist_albums: ArtistAlbums = serde_json::from_str(&response)?;
+
+        // The albums of an artist do not contain the UPC, so they have to be fetched again.
+        // Spotify allows fetching up to 20 albums per request.
+        let mut albums = Vec::new();
+        for chunk in artist_albums.items.chunks(20) {
+            let ids: Vec<&str> = chunk.iter().map(|item| item.id.as_str()).collect();
+            let response = self
+                .client
+                .get(format!(
+                    "{}/albums?ids={}&market={}",
+                    Self::BASE_URL,
+                    ids.join(","),
+                    &artist_link.country_code.alpha2
+                ))
+                .bearer_auth(self.get_bearer_token().await?)
+                .send_with_retry()
+                .await?;
+            let result: Albums = serde_json::from_str(&response)?;
+
+            for album in result.albums {
+                if let ExternalId::UPC(upc) = album.external_ids {
+                    albums.push(AlbumData::with_limited_info(&album.name, &upc));
+                }
+            }
+        }
+
+        Ok(ArtistData::new(&artist.name, albums))
     }
 
-    pub async fn get_artist_data(&self, _artist_link: ShareLink) -> Result<ArtistData, ApiError> {
-        todo!()
+    pub async fn get_artist_link(
+        &self,
+        artist_data: &ArtistData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct ArtistSearch {
+            artists: Artists,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct Artists {
+            items: Vec<Item>,
+        }
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search?q={}&type=artist&market={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&artist_data.display_name),
+                country_code.alpha2,
+                Self::MAX_ARTIST_CANDIDATES
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let result: ArtistSearch = serde_json::from_str(&response)?;
+
+        // Artist names are not unique, so every candidate has to be confirmed by comparing the
+        // discographies.
+        let mut candidate_error = None;
+        for item in result.artists.items {
+            let candidate = ShareLink::new(
+                LinkType::Spotify,
+                ShareObject::Artist,
+                &item.id,
+                country_code,
+            );
+            let candidate_data = match self.get_artist_data(&candidate).await {
+                Ok(candidate_data) => candidate_data,
+                // a candidate that can not be fetched must not prevent checking the others
+                Err(err) => {
+                    candidate_error = Some(err);
+                    continue;
+                }
+            };
+            if self.matcher.artists_match(artist_data, &candidate_data) {
+                return Ok(candidate);
+            }
+        }
+
+        // if a candidate failed, it might have been the artist
+        Err(candidate_error.unwrap_or(ApiError::UnsuccessfulConversion))
+    }
+
+    pub async fn get_playlist_data(
+        &self,
+        playlist_link: &ShareLink,
+    ) -> Result<PlaylistData, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct PlaylistQuery {
+            name: String,
+            tracks: Tracks,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct Tracks {
+            items: Vec<PlaylistItem>,
+            next: Option<String>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct PlaylistItem {
+            // local files and unavailable tracks are returned as null
+            track: Option<TrackInfo>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct TrackInfo {
+            name: String,
+            duration_ms: u64,
+            external_ids: TrackIds,
+            artists: Vec<Artist>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct TrackIds {
+            isrc: Option<String>,
+        }
+
+        if playlist_link.link_type != LinkType::Spotify
+            || playlist_link.share_obj != ShareObject::Playlist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/playlists/{}?market={}",
+                Self::BASE_URL,
+                &playlist_link.id,
+                &playlist_link.country_code.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+        let result: PlaylistQuery = serde_json::from_str(&response)?;
+
+        let mut songs = Vec::new();
+        let mut tracks = result.tracks;
+        loop {
+            for track in tracks.items.into_iter().filter_map(|item| item.track) {
+                let artists = track
+                    .artists
+                    .iter()
+                    .map(|artist| ArtistData::without_albums(&artist.name))
+                    .collect();
+                songs.push(SongData::new(
+                    &track.name,
+                    &track.external_ids.isrc.unwrap_or_default(),
+                    Duration::from_millis(track.duration_ms).as_secs(),
+                    Vec::new(),
+                    artists,
+                ));
+            }
+
+            // one song more than the maximum shows that the playlist is truncated
+            if songs.len() > Self::MAX_PLAYLIST_SIZE {
+                break;
+            }
+
+            // the following pages only contain the tracks object
+            match tracks.next {
+                None => break,
+                Some(next) => {
+                    let response = self
+                        .client
+                        .get(next)
+                        .bearer_auth(self.get_bearer_token().await?)
+                        .send_with_retry()
+                        .await?;
+                    tracks = serde_json::from_str(&response)?;
+                }
+            }
+        }
+
+        let mut playlist = PlaylistData::new(&result.name, songs);
+        playlist.truncate(Self::MAX_PLAYLIST_SIZE);
+        Ok(playlist)
     }
 
     pub async fn get_album_link(
@@ -174,14 +408,13 @@ impl SpotifyApi {
         let response = self
             .client
             .get(format!(
-                "{}/search?q=upc:{}&type=album",
+                "{}/search?q=upc:{}&type=album&market={}",
                 Self::BASE_URL,
-                album_data.upc
+                album_data.upc,
+                country_code.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: AlbumSearch = serde_json::from_str(&response)?;
@@ -214,14 +447,13 @@ impl SpotifyApi {
         let response = self
             .client
             .get(format!(
-                "{}/search?q=isrc:{}&type=track",
+                "{}/search?q=isrc:{}&type=track&market={}",
                 Self::BASE_URL,
-                song_data.isrc
+                song_data.isrc,
+                country_code.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: TrackSearch = serde_json::from_str(&response)?;
@@ -236,10 +468,147 @@ impl SpotifyApi {
             &country_code,
         ))
     }
+    /// Searches for a song by its title and artist. This is the fallback for songs, which can
+    /// not be found by their ISRC, e.g. because of regional re-releases.
+    pub async fn search_song_link(
+        &self,
+        song_data: &SongData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct TrackSearch {
+            tracks: Tracks,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct Tracks {
+            items: Vec<TrackInfo>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct TrackInfo {
+            id: String,
+            name: String,
+            duration_ms: u64,
+            artists: Vec<Artist>,
+        }
+
+        let artist = match song_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("track:{} artist:{}", song_data.norm_name(), artist);
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search?q={}&type=track&market={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                country_code.alpha2,
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let result: TrackSearch = serde_json::from_str(&response)?;
+        for item in result.tracks.items {
+            let artists = item
+                .artists
+                .iter()
+                .map(|artist| ArtistData::without_albums(&artist.name))
+                .collect();
+            let candidate = SongData::new(
+                &item.name,
+                "",
+                Duration::from_millis(item.duration_ms).as_secs(),
+                Vec::new(),
+                artists,
+            );
+
+            if self.matcher.songs_match(song_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::Spotify,
+                    ShareObject::Song,
+                    &item.id,
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
+    /// Searches for an album by its title and artist. This is the fallback for albums, which can
+    /// not be found by their UPC.
+    pub async fn search_album_link(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct AlbumSearch {
+            albums: Albums,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct Albums {
+            items: Vec<AlbumInfo>,
+        }
+        #[derive(Deserialize, Debug, Clone)]
+        struct AlbumInfo {
+            id: String,
+            name: String,
+            artists: Vec<Artist>,
+        }
+
+        let artist = match album_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("album:{} artist:{}", album_data.norm_name(), artist);
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/search?q={}&type=album&market={}&limit={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                country_code.alpha2,
+                Self::MAX_SEARCH_CANDIDATES
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let result: AlbumSearch = serde_json::from_str(&response)?;
+        for item in result.albums.items {
+            let artists = item
+                .artists
+                .iter()
+                .map(|artist| ArtistData::without_albums(&artist.name))
+                .collect();
+            let candidate = AlbumData::new(&item.name, "", Vec::new(), artists);
+
+            if self.matcher.albums_match(album_data, &candidate) {
+                return Ok(ShareLink::new(
+                    LinkType::Spotify,
+                    ShareObject::Album,
+                    &item.id,
+                    country_code,
+                ));
+            }
+        }
+
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
     const PREFERRED_MAX_IMAGE_SIZE: u16 = 800;
     const PREFERRED_MIN_IMAGE_SIZE: u16 = 300;
 
-    pub async fn get_cover_art(&self, album_data: &AlbumData) -> Result<String, ApiError> {
+    pub async fn get_cover_art(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
         #[derive(Deserialize, Debug, Clone)]
         struct AlbumSearch {
             albums: Album,
@@ -263,14 +632,13 @@ impl SpotifyApi {
         let response = self
             .client
             .get(format!(
-                "{}/search?q=upc:{}&type=album",
+                "{}/search?q=upc:{}&type=album&market={}",
                 Self::BASE_URL,
-                album_data.upc
+                album_data.upc,
+                country_code.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: AlbumSearch = serde_json::from_str(&response)?;
@@ -293,4 +661,186 @@ impl SpotifyApi {
         }
         Ok(chosen_image_link)
     }
+
+    /// The countries out of `countries` in which the song or album is available according to
+    /// its `available_markets`.
+    pub async fn get_available_countries(
+        &self,
+        link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        #[derive(Deserialize, Debug, Clone)]
+        struct Markets {
+            available_markets: Option<Vec<String>>,
+        }
+
+        let endpoint = match link.share_obj {
+            ShareObject::Song => "tracks",
+            ShareObject::Album => "albums",
+            ShareObject::Artist | ShareObject::Playlist => {
+                return Err(ApiError::UnsupportedFeature);
+            }
+        };
+        // the markets are only listed if the request is not restricted to a market
+        let response = self
+            .client
+            .get(format!("{}/{}/{}", Self::BASE_URL, endpoint, link.id))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let result: Markets = serde_json::from_str(&response)?;
+        let markets = result
+            .available_markets
+            .ok_or(ApiError::IncorrectAttributes)?;
+        Ok(countries
+            .iter()
+            .filter(|country| markets.iter().any(|market| market == country.alpha2))
+            .copied()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl MusicProvider for SpotifyApi {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            link_type: LinkType::Spotify,
+            url: "https://spotify.com",
+            asset_name: "spotify",
+            parses: &ShareObject::ALL,
+            converts_to: &ShareObject::ALL,
+        }
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Song => Ok(Data::Song(self.get_song_data(link).await?)),
+            ShareObject::Album => Ok(Data::Album(self.get_album_data(link).await?)),
+            ShareObject::Artist => Ok(Data::Artist(self.get_artist_data(link).await?)),
+            ShareObject::Playlist => Ok(Data::Playlist(self.get_playlist_data(link).await?)),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.get_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.get_album_link(album_data, country_code).await,
+            Data::Artist(artist_data) => self.get_artist_link(artist_data, country_code).await,
+            // playlists are user specific and can only be converted track by track
+            Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn search_data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) => self.search_song_link(song_data, country_code).await,
+            Data::Album(album_data) => self.search_album_link(album_data, country_code).await,
+            // artists are already looked up by searching for them
+            Data::Artist(_) | Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn get_artwork(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        let album_data = match data {
+            Data::Song(song_data) => song_data
+                .albums
+                .first()
+                .ok_or(ApiError::IncorrectAttributes)?,
+            Data::Album(album_data) => album_data,
+            Data::Artist(_) | Data::Playlist(_) => return Err(ApiError::UnsupportedFeature),
+        };
+        self.get_cover_art(album_data, country_code).await
+    }
+
+    async fn available_countries(
+        &self,
+        link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        self.get_available_countries(link, countries).await
+    }
+}
+
+pub struct SpotifyLinks;
+
+impl LinkFormat for SpotifyLinks {
+    fn link_type(&self) -> LinkType {
+        LinkType::Spotify
+    }
+
+    fn name(&self) -> &'static str {
+        "Spotify"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["open.spotify.com", "play.spotify.com"]
+    }
+
+    fn app_schemes(&self) -> &'static [&'static str] {
+        &["spotify"]
+    }
+
+    /// The URIs of the desktop client have no host, only a path like `track:{id}`.
+    fn app_link_segments<'a>(&self, url: &'a Url) -> Result<Vec<&'a str>, ShareLinkError> {
+        if !url.cannot_be_a_base() {
+            return Err(ShareLinkError::MalformedOrInvalidLink);
+        }
+        let segments: Vec<&str> = url.path().split(':').collect();
+        // old playlist URIs still contain the owner, e.g. `user:{name}:playlist:{id}`
+        Ok(match segments.as_slice() {
+            ["user", _, rest @ ..] if !rest.is_empty() => rest.to_vec(),
+            _ => segments,
+        })
+    }
+
+    /// `/[embed/][intl-{locale}/]{kind}/{id}`, where the locale may be a country, e.g. `intl-de`,
+    /// or contain one, e.g. `intl-pt_br`.
+    fn parse_path(&self, _url: &Url, segments: &[&str]) -> Result<PathParts, ShareLinkError> {
+        let segments = match segments {
+            ["embed", rest @ ..] => rest,
+            _ => segments,
+        };
+        // languages that are no country, e.g. `intl-ja`, get the default country
+        match segments {
+            [locale, kind, id] => match locale.strip_prefix("intl-") {
+                Some(language) => Ok((
+                    country_of_locale(language),
+                    share_object(kind)?,
+                    id.to_string(),
+                )),
+                None => Err(ShareLinkError::MalformedOrInvalidLink),
+            },
+            [kind, id] => Ok((None, share_object(kind)?, id.to_string())),
+            _ => Err(ShareLinkError::MalformedOrInvalidLink),
+        }
+    }
+
+    fn to_url(&self, link: &ShareLink) -> String {
+        format!(
+            "https://open.spotify.com/{}/{}",
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn to_app_uri(&self, link: &ShareLink) -> String {
+        format!("spotify:{}:{}", self.kind(link.share_obj), link.id)
+    }
+
+    fn android_package(&self) -> &'static str {
+        "com.spotify.music"
+    }
 }
@@ -2,19 +2,26 @@ use std::sync::Arc;
 
 use tokio::sync::RwLock;
 
-use reqwest::Client;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use reqwest::{Client, Url};
 use rust_iso3166::CountryCode;
-use serde::Deserialize;
+use serde::{Deserialize, de::IgnoredAny};
 
 use crate::{
     config::ClientCredentials,
-    share_link::{LinkType, ShareLink, ShareObject},
-    shared_item::{AlbumData, ArtistData, SongData},
+    share_link::{
+        LinkFormat, LinkType, PathParts, ShareLink, ShareLinkError, ShareObject, path_segments,
+        share_object,
+    },
+    shared_item::{self, AlbumData, ArtistData, PlaylistData, SongData, matching::Matcher},
 };
 
 use super::{
     ApiError,
     authorization::{AccessToken, AuthorizationError},
+    provider::{MusicProvider, ProviderInfo},
+    request::RequestExt,
 };
 
 #[derive(Deserialize, Debug, Clone)]
@@ -28,6 +35,27 @@ struct FilterQuery {
     data: Vec<Data>,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+struct SearchQuery {
+    data: Vec<Identifier>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Identifier {
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct RelationshipQuery {
+    links: Links,
+    included: Vec<Data>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct Links {
+    next: Option<String>,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 struct Data {
     id: String,
@@ -44,6 +72,10 @@ enum Attributes {
     Albums(AlbumAttrs),
     #[serde(rename = "artists")]
     Artists(ArtistAttrs),
+    #[serde(rename = "playlists")]
+    Playlists(PlaylistAttrs),
+    #[serde(rename = "videos")]
+    Videos(IgnoredAny),
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -65,24 +97,32 @@ struct ArtistAttrs {
     pub name: String,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+struct PlaylistAttrs {
+    pub name: String,
+}
+
 #[derive(Clone)]
 pub struct TidalApi {
     client: Client,
     creds: ClientCredentials,
     access_token: Arc<RwLock<AccessToken>>,
+    matcher: Matcher,
 }
 
-const ISO_DURATION_ERR_MSG: &'static str = "Duration does not follow iso8601.";
-const INCLUDE_ERR_MSG: &'static str =
-    "Request with include query parameter did not return included in response.";
-
 impl TidalApi {
     const BASE_URL: &'static str = "https://openapi.tidal.com/v2";
     const AUTH_ENDPOINT: &'static str = "https://auth.tidal.com/v1/oauth2/token";
+    const MAX_ARTIST_CANDIDATES: usize = 3;
+    const MAX_PLAYLIST_SIZE: usize = 100;
+    const MAX_SEARCH_CANDIDATES: usize = 3;
+    // Tidal allows only about 10 concurrent requests for apps in development mode
+    const MAX_CONCURRENT_REQUESTS: usize = 5;
 
     pub async fn new(
         client: &Client,
         credentials: &ClientCredentials,
+        matcher: &Matcher,
     ) -> Result<Self, AuthorizationError> {
         Ok(Self {
             client: client.clone(),
@@ -90,6 +130,7 @@ impl TidalApi {
             access_token: Arc::new(RwLock::new(
                 AccessToken::new(client, credentials, Self::AUTH_ENDPOINT).await?,
             )),
+            matcher: matcher.clone(),
         })
     }
 
@@ -122,9 +163,7 @@ impl TidalApi {
                 cc.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let results: QueryResult = serde_json::from_str(&response)?;
@@ -135,18 +174,19 @@ impl TidalApi {
         };
         let song_name = song_attrs.title;
         let song_isrc = song_attrs.isrc;
-        let song_dur = iso8601_to_seconds(&song_attrs.duration).unwrap();
+        let song_dur =
+            iso8601_to_seconds(&song_attrs.duration).map_err(|_| ApiError::IncorrectAttributes)?;
 
         let mut albums = Vec::new();
         let mut artists = Vec::new();
 
-        for include in results.included.expect(INCLUDE_ERR_MSG) {
+        for include in results.included.ok_or(ApiError::IncorrectAttributes)? {
             match include.attributes {
-                Attributes::Tracks(_) => return Err(ApiError::IncorrectAttributes),
                 Attributes::Albums(attrs) => {
                     albums.push(AlbumData::with_limited_info(&attrs.title, &attrs.upc))
                 }
                 Attributes::Artists(attrs) => artists.push(ArtistData::without_albums(&attrs.name)),
+                _ => return Err(ApiError::IncorrectAttributes),
             }
         }
 
@@ -172,9 +212,7 @@ impl TidalApi {
                 cc.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let results: QueryResult = serde_json::from_str(&response)?;
@@ -187,7 +225,7 @@ impl TidalApi {
         let album_upc = album_attrs.upc;
         let mut songs: Vec<SongData> = Vec::new();
         let mut artists: Vec<ArtistData> = Vec::new();
-        let includes = results.included.expect(INCLUDE_ERR_MSG);
+        let includes = results.included.ok_or(ApiError::IncorrectAttributes)?;
 
         for include in includes {
             match include.attributes {
@@ -195,7 +233,8 @@ impl TidalApi {
                 Attributes::Tracks(attrs) => songs.push(SongData::new(
                     &attrs.title,
                     &attrs.isrc,
-                    iso8601_to_seconds(&attrs.duration).expect(ISO_DURATION_ERR_MSG),
+                    iso8601_to_seconds(&attrs.duration)
+                        .map_err(|_| ApiError::IncorrectAttributes)?,
                     Vec::new(),
                     Vec::new(),
                 )),
@@ -206,18 +245,7 @@ impl TidalApi {
         Ok(AlbumData::new(&album_name, &album_upc, songs, artists))
     }
 
-    pub async fn get_artist_data(&self, artist_link: ShareLink) -> Result<ArtistData, ApiError> {
-        #[derive(Deserialize, Debug, Clone)]
-        struct RelationshipResults {
-            links: Link,
-            included: Vec<Data>,
-        }
-
-        #[derive(Deserialize, Debug, Clone)]
-        struct Link {
-            next: Option<String>,
-        }
-
+    pub async fn get_artist_data(&self, artist_link: &ShareLink) -> Result<ArtistData, ApiError> {
         if artist_link.link_type != LinkType::Tidal || artist_link.share_obj != ShareObject::Artist
         {
             return Err(ApiError::UnsuitableLink);
@@ -235,9 +263,7 @@ impl TidalApi {
                 cc.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let results: QueryResult = serde_json::from_str(&response)?;
@@ -258,12 +284,10 @@ impl TidalApi {
                 cc.alpha2
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
-        let mut results: RelationshipResults = serde_json::from_str(&response)?;
+        let mut results: RelationshipQuery = serde_json::from_str(&response)?;
 
         // TODO: This needs a better solution
         let max_requests = 3; // in development mode a tidal application can make max 10 requests
@@ -293,9 +317,7 @@ impl TidalApi {
                         .client
                         .get(format!("{}{}", Self::BASE_URL, &link))
                         .bearer_auth(self.get_bearer_token().await?)
-                        .send()
-                        .await?
-                        .text()
+                        .send_with_retry()
                         .await?;
 
                     request_counter += 1;
@@ -307,6 +329,134 @@ impl TidalApi {
         return Ok(ArtistData::new(&artist_name, albums));
     }
 
+    pub async fn get_playlist_data(
+        &self,
+        playlist_link: &ShareLink,
+    ) -> Result<PlaylistData, ApiError> {
+        if playlist_link.link_type != LinkType::Tidal
+            || playlist_link.share_obj != ShareObject::Playlist
+        {
+            return Err(ApiError::UnsuitableLink);
+        }
+
+        let cc = playlist_link.country_code;
+        let id = playlist_link.id.clone();
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/playlists/{}?countryCode={}",
+                Self::BASE_URL,
+                id,
+                cc.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let results: QueryResult = serde_json::from_str(&response)?;
+        let playlist_attrs = match results.data.attributes {
+            Attributes::Playlists(attrs) => attrs,
+            _ => return Err(ApiError::IncorrectAttributes),
+        };
+
+        let mut songs: Vec<SongData> = Vec::new();
+        let response = self
+            .client
+            .get(format!(
+                "{}/playlists/{}/relationships/items?countryCode={}&include=items",
+                Self::BASE_URL,
+                id,
+                cc.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let mut results: RelationshipQuery = serde_json::from_str(&response)?;
+
+        loop {
+            for item in results.included {
+                // playlists can also contain videos, which can not be converted
+                if let Attributes::Tracks(attrs) = item.attributes {
+                    songs.push(SongData::new(
+                        &attrs.title,
+                        &attrs.isrc,
+                        iso8601_to_seconds(&attrs.duration)
+                            .map_err(|_| ApiError::IncorrectAttributes)?,
+                        Vec::new(),
+                        Vec::new(),
+                    ));
+                }
+            }
+
+            // one song more than the maximum shows that the playlist is truncated
+            if songs.len() > Self::MAX_PLAYLIST_SIZE {
+                break;
+            }
+
+            match results.links.next {
+                None => break,
+                Some(link) => {
+                    let response = self
+                        .client
+                        .get(format!("{}{}", Self::BASE_URL, &link))
+                        .bearer_auth(self.get_bearer_token().await?)
+                        .send_with_retry()
+                        .await?;
+
+                    results = serde_json::from_str(&response)?;
+                }
+            }
+        }
+
+        let mut playlist = PlaylistData::new(&playlist_attrs.name, songs);
+        playlist.truncate(Self::MAX_PLAYLIST_SIZE);
+        Ok(playlist)
+    }
+
+    pub async fn get_artist_link(
+        &self,
+        artist_data: &ArtistData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/searchResults/{}/relationships/artists?countryCode={}",
+                Self::BASE_URL,
+                urlencoding::encode(&artist_data.display_name),
+                country_code.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let results: SearchQuery = serde_json::from_str(&response)?;
+
+        // Artist names are not unique, so every candidate has to be confirmed by comparing the
+        // discographies.
+        let mut candidate_error = None;
+        for item in results.data.iter().take(Self::MAX_ARTIST_CANDIDATES) {
+            let candidate =
+                ShareLink::new(LinkType::Tidal, ShareObject::Artist, &item.id, country_code);
+            let candidate_data = match self.get_artist_data(&candidate).await {
+                Ok(candidate_data) => candidate_data,
+                // a candidate that can not be fetched must not prevent checking the others
+                Err(err) => {
+                    candidate_error = Some(err);
+                    continue;
+                }
+            };
+            if self.matcher.artists_match(artist_data, &candidate_data) {
+                return Ok(candidate);
+            }
+        }
+
+        // if a candidate failed, it might have been the artist
+        Err(candidate_error.unwrap_or(ApiError::UnsuccessfulConversion))
+    }
+
     pub async fn get_song_link(
         &self,
         song_data: &SongData,
@@ -321,15 +471,17 @@ impl TidalApi {
                 song_data.isrc
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let results: FilterQuery = serde_json::from_str(&response)?;
-        for item in results.data {
-            let share_link = ShareLink::new(LinkType::Tidal, ShareObject::Song, &item.id, country_code);
-                return Ok(share_link);
+        if let Some(item) = results.data.first() {
+            return Ok(ShareLink::new(
+                LinkType::Tidal,
+                ShareObject::Song,
+                &item.id,
+                country_code,
+            ));
         }
         Err(ApiError::UnsuccessfulConversion)
     }
@@ -348,9 +500,7 @@ impl TidalApi {
                 album_data.upc
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let results: FilterQuery = serde_json::from_str(&response)?;
@@ -366,6 +516,104 @@ impl TidalApi {
         }
     }
 
+    /// Searches for a song by its title and artist. This is the fallback for songs, which can
+    /// not be found by their ISRC, e.g. because of regional re-releases.
+    pub async fn search_song_link(
+        &self,
+        song_data: &SongData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let artist = match song_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("{} {}", song_data.norm_name(), artist);
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/searchResults/{}/relationships/tracks?countryCode={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                country_code.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let results: SearchQuery = serde_json::from_str(&response)?;
+
+        // the search only returns identifiers, so the candidates have to be fetched one by one
+        let mut candidate_error = None;
+        for item in results.data.iter().take(Self::MAX_SEARCH_CANDIDATES) {
+            let candidate =
+                ShareLink::new(LinkType::Tidal, ShareObject::Song, &item.id, country_code);
+            let candidate_data = match self.get_song_data(&candidate).await {
+                Ok(candidate_data) => candidate_data,
+                // a candidate that can not be fetched must not prevent checking the others
+                Err(err) => {
+                    candidate_error = Some(err);
+                    continue;
+                }
+            };
+            if self.matcher.songs_match(song_data, &candidate_data) {
+                return Ok(candidate);
+            }
+        }
+
+        // if a candidate failed, it might have been the match
+        Err(candidate_error.unwrap_or(ApiError::UnsuccessfulConversion))
+    }
+
+    /// Searches for an album by its title and artist. This is the fallback for albums, which can
+    /// not be found by their UPC.
+    pub async fn search_album_link(
+        &self,
+        album_data: &AlbumData,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        let artist = match album_data.artists().first() {
+            Some(artist) => artist.norm_name(),
+            None => return Err(ApiError::UnsuccessfulConversion),
+        };
+        let query = format!("{} {}", album_data.norm_name(), artist);
+
+        let response = self
+            .client
+            .get(format!(
+                "{}/searchResults/{}/relationships/albums?countryCode={}",
+                Self::BASE_URL,
+                urlencoding::encode(&query),
+                country_code.alpha2
+            ))
+            .bearer_auth(self.get_bearer_token().await?)
+            .send_with_retry()
+            .await?;
+
+        let results: SearchQuery = serde_json::from_str(&response)?;
+
+        // the search only returns identifiers, so the candidates have to be fetched one by one
+        let mut candidate_error = None;
+        for item in results.data.iter().take(Self::MAX_SEARCH_CANDIDATES) {
+            let candidate =
+                ShareLink::new(LinkType::Tidal, ShareObject::Album, &item.id, country_code);
+            let candidate_data = match self.get_album_data(&candidate).await {
+                Ok(candidate_data) => candidate_data,
+                // a candidate that can not be fetched must not prevent checking the others
+                Err(err) => {
+                    candidate_error = Some(err);
+                    continue;
+                }
+            };
+            if self.matcher.albums_match(album_data, &candidate_data) {
+                return Ok(candidate);
+            }
+        }
+
+        // if a candidate failed, it might have been the match
+        Err(candidate_error.unwrap_or(ApiError::UnsuccessfulConversion))
+    }
+
     const PREFERRED_MAX_IMAGE_SIZE: u16 = 800;
     const PREFERRED_MIN_IMAGE_SIZE: u16 = 300;
 
@@ -405,9 +653,7 @@ impl TidalApi {
                 album_data.upc
             ))
             .bearer_auth(self.get_bearer_token().await?)
-            .send()
-            .await?
-            .text()
+            .send_with_retry()
             .await?;
 
         let result: CoverArtQueryResult = serde_json::from_str(&response)?;
@@ -432,10 +678,55 @@ impl TidalApi {
 
         Ok(chosen_image_link)
     }
+
+    /// The countries out of `countries` in which the song or album is available. Tidal answers
+    /// with 404 for items that are not available in the requested country, so every country is
+    /// queried on its own.
+    pub async fn get_available_countries(
+        &self,
+        link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        let endpoint = match link.share_obj {
+            ShareObject::Song => "tracks",
+            ShareObject::Album => "albums",
+            ShareObject::Artist | ShareObject::Playlist => {
+                return Err(ApiError::UnsupportedFeature);
+            }
+        };
+        let token = self.get_bearer_token().await?;
+
+        let queries = stream::iter(countries.iter().copied()).map(|country| {
+            self.client
+                .get(format!(
+                    "{}/{}/{}?countryCode={}",
+                    Self::BASE_URL,
+                    endpoint,
+                    link.id,
+                    country.alpha2
+                ))
+                .bearer_auth(&token)
+                .send_with_retry()
+        });
+
+        let mut available = Vec::new();
+        let results: Vec<_> = queries
+            .buffered(Self::MAX_CONCURRENT_REQUESTS)
+            .collect()
+            .await;
+        for (country, result) in countries.iter().zip(results) {
+            match result {
+                Ok(_) => available.push(*country),
+                Err(ApiError::UnexpectedStatus(404)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(available)
+    }
 }
 
 fn iso8601_to_seconds(iso8601_duration: &str) -> Result<u64, String> {
-    let iso_dur = iso8601::duration(iso8601_duration).unwrap();
+    let iso_dur = iso8601::duration(iso8601_duration)?;
     match iso_dur {
         iso8601::Duration::Weeks(_) => {
             return Err(String::from("Week long durations  are not supported."));
@@ -464,3 +755,149 @@ fn iso8601_to_seconds(iso8601_duration: &str) -> Result<u64, String> {
         }
     }
 }
+
+#[async_trait]
+impl MusicProvider for TidalApi {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            link_type: LinkType::Tidal,
+            url: "https://tidal.com",
+            asset_name: "tidal",
+            parses: &ShareObject::ALL,
+            converts_to: &ShareObject::ALL,
+        }
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<shared_item::Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Song => Ok(shared_item::Data::Song(self.get_song_data(link).await?)),
+            ShareObject::Album => Ok(shared_item::Data::Album(self.get_album_data(link).await?)),
+            ShareObject::Artist => Ok(shared_item::Data::Artist(self.get_artist_data(link).await?)),
+            ShareObject::Playlist => Ok(shared_item::Data::Playlist(
+                self.get_playlist_data(link).await?,
+            )),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &shared_item::Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            shared_item::Data::Song(song_data) => self.get_song_link(song_data, country_code).await,
+            shared_item::Data::Album(album_data) => {
+                self.get_album_link(album_data, country_code).await
+            }
+            shared_item::Data::Artist(artist_data) => {
+                self.get_artist_link(artist_data, country_code).await
+            }
+            // playlists are user specific and can only be converted track by track
+            shared_item::Data::Playlist(_) => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn search_data_to_link(
+        &self,
+        data: &shared_item::Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            shared_item::Data::Song(song_data) => {
+                self.search_song_link(song_data, country_code).await
+            }
+            shared_item::Data::Album(album_data) => {
+                self.search_album_link(album_data, country_code).await
+            }
+            // artists are already looked up by searching for them
+            shared_item::Data::Artist(_) | shared_item::Data::Playlist(_) => {
+                Err(ApiError::UnsupportedFeature)
+            }
+        }
+    }
+
+    async fn get_artwork(
+        &self,
+        data: &shared_item::Data,
+        country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        let album_data = match data {
+            shared_item::Data::Song(song_data) => song_data
+                .albums
+                .first()
+                .ok_or(ApiError::IncorrectAttributes)?,
+            shared_item::Data::Album(album_data) => album_data,
+            shared_item::Data::Artist(_) | shared_item::Data::Playlist(_) => {
+                return Err(ApiError::UnsupportedFeature);
+            }
+        };
+        self.get_cover_art(album_data, country_code).await
+    }
+
+    async fn available_countries(
+        &self,
+        link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        self.get_available_countries(link, countries).await
+    }
+}
+
+pub struct TidalLinks;
+
+impl LinkFormat for TidalLinks {
+    fn link_type(&self) -> LinkType {
+        LinkType::Tidal
+    }
+
+    fn name(&self) -> &'static str {
+        "Tidal"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["tidal.com", "listen.tidal.com"]
+    }
+
+    fn app_schemes(&self) -> &'static [&'static str] {
+        &["tidal"]
+    }
+
+    /// The kind is the host, e.g. `tidal://track/{id}`.
+    fn app_link_segments<'a>(&self, url: &'a Url) -> Result<Vec<&'a str>, ShareLinkError> {
+        let host = url
+            .host_str()
+            .ok_or(ShareLinkError::MalformedOrInvalidLink)?;
+        let mut segments = vec![host];
+        segments.extend(path_segments(url));
+        Ok(segments)
+    }
+
+    /// `/[browse/]{kind}/{id}[/u]`. Tidal links do not contain a country.
+    fn parse_path(&self, _url: &Url, segments: &[&str]) -> Result<PathParts, ShareLinkError> {
+        // links shared from the app end with `/u`
+        let segments = match segments {
+            [rest @ .., "u"] => rest,
+            _ => segments,
+        };
+        match segments {
+            ["browse", kind, id] | [kind, id] => Ok((None, share_object(kind)?, id.to_string())),
+            _ => Err(ShareLinkError::MalformedOrInvalidLink),
+        }
+    }
+
+    fn to_url(&self, link: &ShareLink) -> String {
+        format!(
+            "https://tidal.com/browse/{}/{}",
+            self.kind(link.share_obj),
+            link.id
+        )
+    }
+
+    fn to_app_uri(&self, link: &ShareLink) -> String {
+        format!("tidal://{}/{}", self.kind(link.share_obj), link.id)
+    }
+
+    fn android_package(&self) -> &'static str {
+        "com.aspiro.tidal"
+    }
+}
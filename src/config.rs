@@ -9,15 +9,17 @@ pub struct ClientCredentials {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AccessToken {
-    secret: String,
+pub struct AppleMusicCredentials {
+    pub team_id: String,
+    pub key_id: String,
+    pub private_key_path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Credentials {
     pub tidal: Option<ClientCredentials>,
     pub spotify: Option<ClientCredentials>,
-    pub apple_music: Option<AccessToken>,
+    pub apple_music: Option<AppleMusicCredentials>,
 }
 
 impl Credentials {
@@ -30,6 +32,49 @@ impl Credentials {
     }
 }
 
+/// Thresholds of the match engine. Lower thresholds find more matches, but also accept more
+/// wrong ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchingConfig {
+    /// Minimum confidence for two songs to be considered the same.
+    pub song_threshold: f64,
+    /// Minimum confidence for two albums to be considered the same.
+    pub album_threshold: f64,
+    /// Minimum confidence for two artists to be considered the same.
+    pub artist_threshold: f64,
+    /// Difference in seconds up to which the durations of two songs count as equal.
+    pub duration_tolerance: u64,
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        Self {
+            song_threshold: 0.75,
+            album_threshold: 0.75,
+            artist_threshold: 0.6,
+            duration_tolerance: 2,
+        }
+    }
+}
+
+/// Settings of the in-memory cache of conversion results.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Maximum number of cached share links. A capacity of 0 disables the cache.
+    pub capacity: usize,
+    /// Seconds after which a cached result is fetched from the streaming services again.
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: 60 * 60,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub credentials: Option<Credentials>,
@@ -37,6 +82,20 @@ pub struct Config {
     pub listen_address_ipv4: Ipv4Addr,
     pub listen_port: u16,
     pub external_addr: Option<String>,
+    #[serde(default)]
+    pub matching: MatchingConfig,
+    /// Seconds after which a streaming service that has not answered yet is left out of a
+    /// conversion.
+    #[serde(default = "Config::default_provider_timeout")]
+    pub provider_timeout: u64,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Path of the SQLite database in which the IDs of songs and albums are stored.
+    #[serde(default = "Config::default_database_path")]
+    pub database_path: String,
+    /// ISO 3166-1 alpha-2 code of the market used for links that do not contain a country.
+    #[serde(default = "Config::default_country")]
+    pub default_country: String,
 }
 
 impl Config {
@@ -62,7 +121,24 @@ impl Config {
             api_password: Some(String::from("")),
             listen_address_ipv4: Ipv4Addr::from_str("0.0.0.0").unwrap(),
             listen_port: 5000,
-            external_addr: Some(String::from("0.0.0.0:5000"))
+            external_addr: Some(String::from("0.0.0.0:5000")),
+            matching: MatchingConfig::default(),
+            provider_timeout: Self::default_provider_timeout(),
+            cache: CacheConfig::default(),
+            database_path: Self::default_database_path(),
+            default_country: Self::default_country(),
         }
     }
+
+    fn default_provider_timeout() -> u64 {
+        5
+    }
+
+    fn default_database_path() -> String {
+        String::from("./interlude.sqlite3")
+    }
+
+    fn default_country() -> String {
+        String::from("US")
+    }
 }
@@ -3,3 +3,4 @@ pub mod shared_item;
 pub mod api;
 pub mod config;
 pub mod server;
+pub mod store;
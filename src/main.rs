@@ -9,9 +9,7 @@ use std::{net::SocketAddr, sync::Arc};
 async fn main() {
     let config = Config::read();
     let client = reqwest::Client::new();
-    let api_clients = Arc::new(
-        ApiClients::new(&client, config.credentials.expect("No credentials found.")).await,
-    );
+    let api_clients = Arc::new(ApiClients::new(&client, &config).await);
     let api_secret = config.api_password.expect("api_password_not set.");
     let ext_addr = config.external_addr.expect("external_addr is not set.");
     let addr = SocketAddr::from((config.listen_address_ipv4, config.listen_port));
@@ -1,6 +1,7 @@
 use http_body_util::{BodyExt, Empty, Full, combinators::BoxBody};
 use mime_guess::from_path;
 use reqwest::Url;
+use rust_iso3166::CountryCode;
 use std::{fmt::Debug, path::Path, sync::Arc};
 use urlencoding::decode;
 
@@ -10,8 +11,13 @@ use hyper::{
 };
 
 use crate::{
-    api::conversion::{ApiClients, convert},
-    server::public_utils::get_providers,
+    api::conversion::{
+        ApiClients, ConversionOptions,
+        availability::{MAX_COUNTRIES, check_availability},
+        convert_with_options,
+    },
+    server::{error_response::ErrorResponse, public_utils::get_providers},
+    share_link::LinkType,
 };
 
 use super::authorization::check_authorization;
@@ -50,7 +56,11 @@ where
                 Some(link) => decode(&link.1).unwrap().to_string(),
                 _none => return Ok(bad_request("Link must be provided")),
             };
-            let return_link = convert(&link, api_clients).await;
+            let options = match conversion_options(&full_url) {
+                Ok(options) => options,
+                Err(err) => return Ok(error_response(&err)),
+            };
+            let return_link = convert_with_options(&link, &options, api_clients).await;
             match return_link {
                 Ok(return_link) => {
                     let body = full(Bytes::from(return_link));
@@ -62,18 +72,55 @@ where
 
                     Ok(response)
                 }
-                Err(err) => {
-                    let body = full(Bytes::from(err.to_string()));
+                Err(err) => Ok(error_response(&ErrorResponse::from(&err))),
+            }
+        }
+        (&Method::GET, "availability") => {
+            let param = |name: &str| {
+                full_url
+                    .query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            };
+            let Some(link) = param("link") else {
+                return Ok(bad_request("Link must be provided"));
+            };
+            let countries: Result<Vec<CountryCode>, ErrorResponse> = param("countries")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|country| !country.trim().is_empty())
+                .map(parse_country)
+                .collect();
+            let countries = match countries {
+                Ok(countries) if countries.is_empty() => {
+                    return Ok(bad_request("Countries must be provided"));
+                }
+                Ok(countries) if countries.len() > MAX_COUNTRIES => {
+                    return Ok(bad_request(&format!(
+                        "At most {} countries can be checked at once",
+                        MAX_COUNTRIES
+                    )));
+                }
+                Ok(countries) => countries,
+                Err(err) => return Ok(error_response(&err)),
+            };
+
+            match check_availability(&link, &countries, api_clients).await {
+                Ok(availability) => {
+                    let body = full(Bytes::from(availability));
                     let response = Response::builder()
-                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .status(StatusCode::OK)
+                        .header("Content-Type", "application/json")
                         .body(body)
                         .unwrap();
+
                     Ok(response)
                 }
+                Err(err) => Ok(error_response(&ErrorResponse::from(&err))),
             }
         }
         (&Method::GET, "providers") => {
-            let providers = serde_json::to_string(&get_providers(ext_addr)).unwrap();
+            let providers = serde_json::to_string(&get_providers(ext_addr, &api_clients)).unwrap();
             let body = full(Bytes::from(providers));
             let response = Response::builder()
                 .status(StatusCode::OK)
@@ -115,21 +162,62 @@ where
                         .unwrap();
                     Ok(response)
                 }
-                Err(_err) => {
-                    let body = full(Bytes::from("File not found"));
-                    let response = Response::builder()
-                        .status(StatusCode::NOT_FOUND)
-                        .header("Content-Type", "text/plain")
-                        .body(body)
-                        .unwrap();
-                    return Ok(response);
-                }
+                Err(_err) => Ok(error_response(&ErrorResponse::not_found("File not found"))),
             }
         }
         _ => Ok(bad_request("Invalid method or resource")),
     }
 }
 
+/// Reads the optional `to`, `order` and `country` query parameters of a conversion.
+fn conversion_options(url: &Url) -> Result<ConversionOptions, ErrorResponse> {
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    let country = param("country")
+        .map(|country| parse_country(&country))
+        .transpose()?;
+    Ok(ConversionOptions {
+        country,
+        to: param("to").map(|to| parse_providers(&to)).transpose()?,
+        order: param("order")
+            .map(|order| parse_providers(&order))
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+fn parse_country(country: &str) -> Result<CountryCode, ErrorResponse> {
+    rust_iso3166::from_alpha2(&country.trim().to_ascii_uppercase()).ok_or_else(|| {
+        ErrorResponse::new(
+            StatusCode::BAD_REQUEST,
+            "invalidCountry",
+            &format!("Not an ISO 3166-1 alpha-2 country code: {}", country),
+        )
+    })
+}
+
+/// Parses a comma separated list of streaming services, e.g. `tidal,deezer`.
+fn parse_providers(value: &str) -> Result<Vec<LinkType>, ErrorResponse> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            LinkType::from_name(name).ok_or_else(|| {
+                ErrorResponse::new(
+                    StatusCode::BAD_REQUEST,
+                    "unknownProvider",
+                    &format!("Unknown streaming service: {}", name),
+                )
+            })
+        })
+        .collect()
+}
+
 pub fn empty() -> BoxBody<Bytes, HyperError> {
     Empty::<Bytes>::new()
         .map_err(|never| match never {})
@@ -142,20 +230,19 @@ pub fn full<T: Into<Bytes>>(data: T) -> BoxBody<Bytes, HyperError> {
         .boxed()
 }
 
-pub fn bad_request(message: &str) -> Response<BoxBody<Bytes, HyperError>> {
-    let body: BoxBody<Bytes, HyperError> = full(Bytes::from(message.to_string()));
+pub fn error_response(error: &ErrorResponse) -> Response<BoxBody<Bytes, HyperError>> {
+    let body: BoxBody<Bytes, HyperError> = full(Bytes::from(error.to_json()));
     Response::builder()
-        .status(StatusCode::BAD_REQUEST)
-        .header("Content-Type", "text/plain")
+        .status(error.status)
+        .header("Content-Type", "application/json")
         .body(body)
         .unwrap()
 }
 
+pub fn bad_request(message: &str) -> Response<BoxBody<Bytes, HyperError>> {
+    error_response(&ErrorResponse::bad_request(message))
+}
+
 pub fn forbidden(message: &str) -> Response<BoxBody<Bytes, HyperError>> {
-    let body: BoxBody<Bytes, HyperError> = full(Bytes::from(message.to_string()));
-    Response::builder()
-        .status(StatusCode::FORBIDDEN)
-        .header("Content-Type", "text/plain")
-        .body(body)
-        .unwrap()
+    error_response(&ErrorResponse::forbidden(message))
 }
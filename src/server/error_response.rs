@@ -0,0 +1,136 @@
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::{
+    api::{
+        ApiError,
+        conversion::{ConversionError, ErrorReason},
+    },
+    share_link::ShareLinkError,
+};
+
+/// The body of every unsuccessful response. `code` is stable and meant to be matched on by
+/// clients, `message` is only meant for humans.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ErrorResponse {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub code: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub details: Option<Value>,
+}
+
+impl ErrorResponse {
+    pub fn new(status: StatusCode, code: &str, message: &str) -> Self {
+        Self {
+            status,
+            code: code.to_owned(),
+            message: message.to_owned(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn bad_request(message: &str) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "badRequest", message)
+    }
+
+    pub fn forbidden(message: &str) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    pub fn not_found(message: &str) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "notFound", message)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Error response should always be valid.")
+    }
+}
+
+impl From<&ShareLinkError> for ErrorResponse {
+    fn from(err: &ShareLinkError) -> Self {
+        let message = err.to_string();
+        match err {
+            ShareLinkError::InvalidUrl => {
+                Self::new(StatusCode::BAD_REQUEST, "invalidUrl", &message)
+            }
+            ShareLinkError::NotAShareLink => {
+                Self::new(StatusCode::UNPROCESSABLE_ENTITY, "notAShareLink", &message)
+            }
+            ShareLinkError::MalformedOrInvalidLink => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "malformedShareLink",
+                &message,
+            ),
+            ShareLinkError::UnresolvableShortLink => Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "unresolvableShortLink",
+                &message,
+            ),
+        }
+    }
+}
+
+impl From<&ApiError> for ErrorResponse {
+    fn from(err: &ApiError) -> Self {
+        let message = err.to_string();
+        let response = match ErrorReason::from(err) {
+            ErrorReason::NotFound => Self::new(StatusCode::NOT_FOUND, "notFound", &message),
+            ErrorReason::Unsupported => {
+                Self::new(StatusCode::UNPROCESSABLE_ENTITY, "unsupported", &message)
+            }
+            ErrorReason::RateLimited => {
+                Self::new(StatusCode::TOO_MANY_REQUESTS, "rateLimited", &message)
+            }
+            ErrorReason::AuthFailure => Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "providerAuthFailure",
+                &message,
+            ),
+            ErrorReason::Timeout => {
+                Self::new(StatusCode::SERVICE_UNAVAILABLE, "providerTimeout", &message)
+            }
+            ErrorReason::Unavailable => Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "providerUnavailable",
+                &message,
+            ),
+            ErrorReason::ProviderError => {
+                Self::new(StatusCode::BAD_GATEWAY, "providerError", &message)
+            }
+        };
+
+        // the status of the streaming service helps with telling its errors apart
+        match err {
+            ApiError::RequestError(err) if err.status().is_some() => response.with_details(
+                json!({ "providerStatus": err.status().map(|status| status.as_u16()) }),
+            ),
+            ApiError::UnexpectedStatus(status) => {
+                response.with_details(json!({ "providerStatus": status }))
+            }
+            ApiError::ServiceError { code, .. } => {
+                response.with_details(json!({ "providerCode": code }))
+            }
+            ApiError::RateLimited {
+                retry_after: Some(seconds),
+            } => response.with_details(json!({ "retryAfter": seconds })),
+            _ => response,
+        }
+    }
+}
+
+impl From<&ConversionError> for ErrorResponse {
+    fn from(err: &ConversionError) -> Self {
+        match err {
+            ConversionError::ApiClient(err) => Self::from(err),
+            ConversionError::Link(err) => Self::from(err),
+        }
+    }
+}
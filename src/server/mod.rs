@@ -1,3 +1,4 @@
 pub mod authorization;
 pub mod connection_utils;
+pub mod error_response;
 pub mod public_utils;
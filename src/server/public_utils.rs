@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{api::conversion::ApiClients, share_link::ShareObject};
+
 #[derive(Serialize, Deserialize)]
 pub struct Provider {
     name: String,
@@ -8,27 +10,30 @@ pub struct Provider {
     logo_url: String,
     #[serde(rename = "iconUrl")]
     icon_url: String,
+    /// The kinds of share links of the streaming service that can be converted.
+    parses: Vec<ShareObject>,
+    /// The kinds of items that can be converted to the streaming service.
+    #[serde(rename = "convertsTo")]
+    converts_to: Vec<ShareObject>,
+    /// Whether links can currently be converted from and to the streaming service.
+    available: bool,
 }
 
-pub fn get_providers(ext_addr: &str) -> Vec<Provider> {
-    vec![
-        Provider {
-            name: "Spotify".to_string(),
-            url: "https://spotify.com".to_string(),
-            logo_url: format!("{}/public/spotify_logo.png", ext_addr),
-            icon_url: format!("{}/public/spotify_icon.png", ext_addr),
-        },
-        Provider {
-            name: "Tidal".to_string(),
-            url: "https://tidal.com".to_string(),
-            logo_url: format!("{}/public/tidal_logo.png", ext_addr),
-            icon_url: format!("{}/public/tidal_icon.png", ext_addr),
-        },
-        Provider {
-            name: "Deezer".to_string(),
-            url: "https://www.deezer.com".to_string(),
-            logo_url: format!("{}/public/deezer_logo.png", ext_addr),
-            icon_url: format!("{}/public/deezer_icon.png", ext_addr),
-        },
-    ]
+pub fn get_providers(ext_addr: &str, api_clients: &ApiClients) -> Vec<Provider> {
+    api_clients
+        .providers()
+        .iter()
+        .map(|provider| {
+            let info = provider.info();
+            Provider {
+                name: info.link_type.to_string(),
+                url: info.url.to_string(),
+                logo_url: format!("{}/public/{}_logo.png", ext_addr, info.asset_name),
+                icon_url: format!("{}/public/{}_icon.png", ext_addr, info.asset_name),
+                parses: info.parses.to_vec(),
+                converts_to: info.converts_to.to_vec(),
+                available: api_clients.is_available(info.link_type),
+            }
+        })
+        .collect()
 }
@@ -1,8 +1,16 @@
-use hyper::header::LOCATION;
-use reqwest::{Client, redirect::Policy};
+use reqwest::Url;
 use rust_iso3166::CountryCode;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use redirect::RedirectResolver;
+
+use crate::api::{
+    apple_music::AppleMusicLinks, deezer::DeezerLinks, spotify::SpotifyLinks, tidal::TidalLinks,
+};
+
+pub mod redirect;
+
 #[derive(Debug, Error)]
 pub enum ShareLinkError {
     #[error("The provided URL is invalid.")]
@@ -11,11 +19,11 @@ pub enum ShareLinkError {
     NotAShareLink,
     #[error("The provided share link is invalid or malformed.")]
     MalformedOrInvalidLink,
-    #[error("Could not parse Deezer link")]
-    ParseDeezerLink,
+    #[error("The short link could not be resolved.")]
+    UnresolvableShortLink,
 }
 
-#[derive(Clone, Debug, Copy, PartialEq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash)]
 pub enum LinkType {
     Spotify,
     Tidal,
@@ -25,20 +33,105 @@ pub enum LinkType {
 
 impl LinkType {
     pub fn to_string(&self) -> String {
-        match self {
-            Self::Tidal => String::from("Tidal"),
-            Self::Spotify => String::from("Spotify"),
-            Self::Deezer => String::from("Deezer"),
-            Self::AppleMusic => String::from("AppleMusic"),
+        String::from(self.format().name())
+    }
+
+    /// Parses the name of a streaming service as used in query parameters, e.g. `tidal` or
+    /// `apple_music`. Case, dashes and underscores are ignored.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| *c != '_' && *c != '-')
+                .collect::<String>()
+                .to_ascii_lowercase()
+        };
+        let name = normalize(name);
+        LINK_FORMATS
+            .iter()
+            .find(|format| normalize(format.name()) == name)
+            .map(|format| format.link_type())
+    }
+
+    /// The package name of the Android app of the streaming service.
+    pub fn android_package(&self) -> &'static str {
+        self.format().android_package()
+    }
+
+    /// How the links of the streaming service look.
+    pub fn format(&self) -> &'static dyn LinkFormat {
+        *LINK_FORMATS
+            .iter()
+            .find(|format| format.link_type() == *self)
+            .expect("Every streaming service should have a link format.")
+    }
+}
+
+/// The link formats of the streaming services, through which share links are parsed and built.
+pub static LINK_FORMATS: [&dyn LinkFormat; 4] =
+    [&SpotifyLinks, &TidalLinks, &DeezerLinks, &AppleMusicLinks];
+
+/// How the share links of a streaming service look. Each streaming service implements it next to
+/// its API client and is listed in [`LINK_FORMATS`].
+pub trait LinkFormat: Send + Sync {
+    fn link_type(&self) -> LinkType;
+
+    /// The name of the streaming service in conversion results, e.g. `AppleMusic`.
+    fn name(&self) -> &'static str;
+
+    /// The hosts of the web links, without `www.`.
+    fn hosts(&self) -> &'static [&'static str];
+
+    /// The schemes of the links that open the app of the streaming service.
+    fn app_schemes(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// The segments of an app link that correspond to the path of its web link.
+    fn app_link_segments<'a>(&self, _url: &'a Url) -> Result<Vec<&'a str>, ShareLinkError> {
+        Err(ShareLinkError::MalformedOrInvalidLink)
+    }
+
+    /// The country, kind and ID of a share link, as found in the segments of its path. The query
+    /// of the link is passed along for streaming services that put the ID there.
+    fn parse_path(&self, url: &Url, segments: &[&str]) -> Result<PathParts, ShareLinkError>;
+
+    /// The kind of the item as it appears in the links of the streaming service.
+    fn kind(&self, share_obj: ShareObject) -> &'static str {
+        match share_obj {
+            ShareObject::Song => "track",
+            ShareObject::Album => "album",
+            ShareObject::Artist => "artist",
+            ShareObject::Playlist => "playlist",
         }
     }
+
+    fn to_url(&self, link: &ShareLink) -> String;
+
+    /// The URI that opens the item in the app of the streaming service, e.g.
+    /// `spotify:track:{id}` or `tidal://track/{id}`.
+    fn to_app_uri(&self, link: &ShareLink) -> String;
+
+    /// The package name of the Android app of the streaming service.
+    fn android_package(&self) -> &'static str;
+
+    /// The URI that the Android app is opened with, which is the app URI unless the app only
+    /// handles web links.
+    fn android_intent_uri(&self, link: &ShareLink) -> String {
+        self.to_app_uri(link)
+    }
 }
 
-#[derive(Clone, Debug, Copy, PartialEq)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum ShareObject {
     Song,
     Album,
     Artist,
+    Playlist,
+}
+
+impl ShareObject {
+    pub const ALL: [ShareObject; 4] = [Self::Song, Self::Album, Self::Artist, Self::Playlist];
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -51,25 +144,13 @@ pub struct ShareLink {
 
 impl ShareLink {
     pub fn to_url(&self) -> String {
-        let obj = match self.share_obj {
-            ShareObject::Album => "album",
-            ShareObject::Song => "track",
-            ShareObject::Artist => "artist",
-        };
-        match self.link_type {
-            LinkType::Tidal => {
-                return format!("https://tidal.com/browse/{}/{}", obj, self.id);
-            }
-            LinkType::Spotify => {
-                return format!("https://open.spotify.com/{}/{}", obj, self.id);
-            }
-            LinkType::AppleMusic => {
-                todo!()
-            }
-            LinkType::Deezer => {
-                return format!("https://www.deezer.com/{}/{}", obj, self.id);
-            }
-        }
+        self.link_type.format().to_url(self)
+    }
+
+    /// The URI that opens the item in the app of the streaming service, e.g.
+    /// `spotify:track:{id}` or `tidal://track/{id}`.
+    pub fn to_app_uri(&self) -> String {
+        self.link_type.format().to_app_uri(self)
     }
 
     pub fn new(
@@ -86,163 +167,96 @@ impl ShareLink {
         }
     }
 
+    /// Parses the share link. Links that do not contain a country are assumed to be from the US.
     pub async fn from_url(url: &str) -> Result<ShareLink, ShareLinkError> {
-        let mut country_code: Option<CountryCode> = None;
-        let mut id = String::new();
-
-        let mut url = url.to_owned();
-        if url.starts_with("https://link.deezer.com/s/") {
-            let client = Client::builder().redirect(Policy::none()).build().unwrap();
-            let response = client
-                .get(url)
-                .send()
-                .await
-                .map_err(|_| ShareLinkError::ParseDeezerLink)?
-                .headers()
-                .get(LOCATION)
-                .unwrap()
-                .clone();
-            let extracted_link = urlencoding::decode(
-                &response.to_str().unwrap()[29..]
-                    .split_once("%3Fhost")
-                    .unwrap()
-                    .0,
-            )
-            .unwrap()
-            .into_owned();
-            url = extracted_link;
-        }
-
-        let mut parts = url.split('/');
-        match parts.next() {
-            Some("https:") => {}
-            Some(_) => return Err(ShareLinkError::InvalidUrl),
-            None => return Err(ShareLinkError::InvalidUrl),
-        }
-
-        match parts.next() {
-            Some("") => {}
-            Some(_) | None => return Err(ShareLinkError::InvalidUrl),
-        }
+        Self::from_url_with_default_country(url, &rust_iso3166::US).await
+    }
 
-        let link_type: Option<LinkType> = match parts.next() {
-            None => return Err(ShareLinkError::InvalidUrl),
-            Some(text) => match text {
-                "open.spotify.com" => Some(LinkType::Spotify),
-                "tidal.com" => Some(LinkType::Tidal),
-                "music.apple.com" => Some(LinkType::AppleMusic),
-                "www.deezer.com" => Some(LinkType::Deezer),
-                _ => return Err(ShareLinkError::NotAShareLink),
-            },
-        };
+    /// Parses the share link. Links that do not contain a country, e.g. Tidal links, get the
+    /// `default_country`.
+    pub async fn from_url_with_default_country(
+        url: &str,
+        default_country: &CountryCode,
+    ) -> Result<ShareLink, ShareLinkError> {
+        let url = RedirectResolver::shared().resolve(url.trim()).await?;
+        Self::parse(&url, default_country)
+    }
 
-        match link_type {
-            Some(LinkType::Spotify) => {
-                let parts_backup = parts.clone();
-                match parts.next() {
-                    Some(text) => {
-                        if text.len() != 7 || !text.starts_with("intl-") {
-                            match text {
-                                "track" | "album" | "artist" => {
-                                    parts = parts_backup;
-                                    country_code = Some(rust_iso3166::from_alpha2("US").unwrap());
-                                }
-                                _ => return Err(ShareLinkError::MalformedOrInvalidLink),
-                            }
-                        } else {
-                            country_code =
-                                match rust_iso3166::from_alpha2(&text[5..].to_ascii_uppercase()) {
-                                    Some(cc) => Some(cc),
-                                    None => return Err(ShareLinkError::MalformedOrInvalidLink),
-                                };
-                        }
-                    }
-                    None => return Err(ShareLinkError::MalformedOrInvalidLink),
-                }
+    fn parse(url: &str, default_country: &CountryCode) -> Result<ShareLink, ShareLinkError> {
+        let url = Url::parse(url).map_err(|_| ShareLinkError::InvalidUrl)?;
+        let (format, segments) = match url.scheme() {
+            "http" | "https" => {
+                // the host is already lowercase, the query and the fragment are not part of the
+                // segments
+                let host = url.host_str().ok_or(ShareLinkError::InvalidUrl)?;
+                let host = host.strip_prefix("www.").unwrap_or(host);
+                let format = LINK_FORMATS
+                    .iter()
+                    .find(|format| format.hosts().contains(&host))
+                    .ok_or(ShareLinkError::NotAShareLink)?;
+                (format, path_segments(&url))
             }
-            Some(LinkType::Tidal) => match parts.next() {
-                Some("browse") => country_code = Some(rust_iso3166::from_alpha2("US").unwrap()),
-                _ => return Err(ShareLinkError::MalformedOrInvalidLink),
-            },
-            Some(LinkType::AppleMusic) => match parts.next() {
-                Some(cc) => {
-                    let cc = rust_iso3166::from_alpha2(&cc.to_ascii_uppercase());
-                    if cc.is_none() {
-                        return Err(ShareLinkError::MalformedOrInvalidLink);
-                    }
-                    country_code = Some(cc.unwrap());
-                }
-                _ => {}
-            },
-            Some(LinkType::Deezer) => {
-                let parts_backup = parts.clone();
-                match parts.next() {
-                    Some(text) => {
-                        if text.len() != 2 {
-                            match text {
-                                "track" | "album" | "artist" => {
-                                    parts = parts_backup;
-                                    country_code = Some(rust_iso3166::from_alpha2("US").unwrap());
-                                }
-                                _ => return Err(ShareLinkError::MalformedOrInvalidLink),
-                            }
-                        } else {
-                            country_code =
-                                match rust_iso3166::from_alpha2(&text.to_ascii_uppercase()) {
-                                    Some(cc) => Some(cc),
-                                    None => return Err(ShareLinkError::MalformedOrInvalidLink),
-                                };
-                        }
-                    }
-                    None => return Err(ShareLinkError::MalformedOrInvalidLink),
-                }
+            scheme => {
+                let format = LINK_FORMATS
+                    .iter()
+                    .find(|format| format.app_schemes().contains(&scheme))
+                    .ok_or(ShareLinkError::InvalidUrl)?;
+                (format, format.app_link_segments(&url)?)
             }
-            None => {} // Cannot be None at this point. Function failes already before.
-        }
-
-        let share_obj: Option<ShareObject> = match parts.next() {
-            Some("track") => Some(ShareObject::Song),
-            Some("album") => Some(ShareObject::Album),
-            Some("artist") => Some(ShareObject::Artist),
-            Some("song") => {
-                if link_type != Some(LinkType::AppleMusic) {
-                    return Err(ShareLinkError::MalformedOrInvalidLink);
-                }
-                Some(ShareObject::Song)
-            }
-            Some(_) | None => return Err(ShareLinkError::MalformedOrInvalidLink),
         };
 
-        // In case of Apple Music we have to consume the next part of the url, which usually
-        // contains the name of the song, album or artist, which we don't need at this point.
-        if link_type == Some(LinkType::AppleMusic) {
-            if parts.next().is_none() {
-                return Err(ShareLinkError::MalformedOrInvalidLink);
-            }
+        let (country_code, share_obj, id) = format.parse_path(&url, &segments)?;
+        if !is_valid_id(&id) {
+            return Err(ShareLinkError::MalformedOrInvalidLink);
         }
 
-        match parts.next() {
-            Some(text) => {
-                for c in text.chars() {
-                    match c {
-                        '?' => {
-                            if id.len() == 0 {
-                                return Err(ShareLinkError::MalformedOrInvalidLink);
-                            }
-                            break;
-                        }
-                        _ => id.push(c),
-                    }
-                }
-            }
-            None => return Err(ShareLinkError::MalformedOrInvalidLink),
-        }
+        Ok(ShareLink::new(
+            format.link_type(),
+            share_obj,
+            &id,
+            &country_code.unwrap_or(*default_country),
+        ))
+    }
+}
 
-        Ok(ShareLink {
-            link_type: link_type.unwrap(),
-            country_code: country_code.unwrap(),
-            share_obj: share_obj.unwrap(),
-            id,
-        })
+/// The segments of the path of a link, without empty ones.
+pub fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// The country, kind and ID of a share link, as found in its path.
+pub type PathParts = (Option<CountryCode>, ShareObject, String);
+
+/// The kind of item of a link, e.g. `track` or `song`.
+pub fn share_object(kind: &str) -> Result<ShareObject, ShareLinkError> {
+    match kind.to_ascii_lowercase().as_str() {
+        "track" | "song" => Ok(ShareObject::Song),
+        "album" => Ok(ShareObject::Album),
+        "artist" => Ok(ShareObject::Artist),
+        "playlist" => Ok(ShareObject::Playlist),
+        _ => Err(ShareLinkError::MalformedOrInvalidLink),
+    }
+}
+
+/// The country of a locale like `de`, `en-gb` or `pt_br`. Languages that are not countries,
+/// e.g. `en`, have none.
+pub fn country_of_locale(locale: &str) -> Option<CountryCode> {
+    let country = match locale.split_once(['-', '_']) {
+        Some((_, country)) => country,
+        None => locale,
+    };
+    if country.len() != 2 {
+        return None;
     }
+    rust_iso3166::from_alpha2(&country.to_ascii_uppercase())
+}
+
+/// IDs are numbers, base62 strings, UUIDs or, for Apple Music playlists, like `pl.u-abc`.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
 }
@@ -0,0 +1,108 @@
+use std::{sync::LazyLock, time::Duration};
+
+use hyper::header::LOCATION;
+use reqwest::{Client, Url, redirect::Policy};
+
+use super::ShareLinkError;
+
+/// Resolves short links, e.g. `https://spotify.link/...`, to the share links they redirect to.
+/// Only hosts on the allow-list are requested and the redirects are followed one by one, so that
+/// a short link can not make the server request arbitrary URLs.
+pub struct RedirectResolver {
+    client: Client,
+    short_link_hosts: Vec<String>,
+    max_hops: usize,
+}
+
+impl RedirectResolver {
+    /// The hosts of the short links of the streaming services.
+    pub const SHORT_LINK_HOSTS: [&'static str; 6] = [
+        "spotify.link",
+        "spoti.fi",
+        "link.deezer.com",
+        "deezer.page.link",
+        "dzr.page.link",
+        "tidal.link",
+    ];
+    const MAX_HOPS: usize = 5;
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub fn new(short_link_hosts: &[&str], max_hops: usize, timeout: Duration) -> Self {
+        Self {
+            client: Client::builder()
+                .redirect(Policy::none())
+                .timeout(timeout)
+                .build()
+                .expect("The redirect client should always be valid."),
+            short_link_hosts: short_link_hosts
+                .iter()
+                .map(|host| host.to_string())
+                .collect(),
+            max_hops,
+        }
+    }
+
+    /// The resolver for the short links of the supported streaming services.
+    pub fn shared() -> &'static Self {
+        static RESOLVER: LazyLock<RedirectResolver> = LazyLock::new(RedirectResolver::default);
+        &RESOLVER
+    }
+
+    /// Follows the redirects of a short link until it leads to a host that is not a short link
+    /// host. Other URLs are returned as they are.
+    pub async fn resolve(&self, url: &str) -> Result<String, ShareLinkError> {
+        let mut next = match Url::parse(url) {
+            Ok(next) if self.is_short_link(&next) => next,
+            // anything else, including invalid URLs, is left to the share link parser
+            _ => return Ok(url.to_owned()),
+        };
+
+        for _ in 0..self.max_hops {
+            next = self.next_hop(&next).await?;
+            if !self.is_short_link(&next) {
+                return Ok(next.to_string());
+            }
+        }
+        Err(ShareLinkError::UnresolvableShortLink)
+    }
+
+    fn is_short_link(&self, url: &Url) -> bool {
+        matches!(url.scheme(), "http" | "https")
+            && url.host_str().is_some_and(|host| {
+                self.short_link_hosts
+                    .iter()
+                    .any(|short_link_host| host.eq_ignore_ascii_case(short_link_host))
+            })
+    }
+
+    async fn next_hop(&self, url: &Url) -> Result<Url, ShareLinkError> {
+        // dynamic links carry their target in the `link` parameter, so they are resolved without a
+        // request
+        if let Some((_, link)) = url.query_pairs().find(|(key, _)| key == "link") {
+            return Url::parse(&link).map_err(|_| ShareLinkError::UnresolvableShortLink);
+        }
+
+        let response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .map_err(|_| ShareLinkError::UnresolvableShortLink)?;
+        if !response.status().is_redirection() {
+            return Err(ShareLinkError::UnresolvableShortLink);
+        }
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(ShareLinkError::UnresolvableShortLink)?;
+        url.join(location)
+            .map_err(|_| ShareLinkError::UnresolvableShortLink)
+    }
+}
+
+impl Default for RedirectResolver {
+    fn default() -> Self {
+        Self::new(&Self::SHORT_LINK_HOSTS, Self::MAX_HOPS, Self::TIMEOUT)
+    }
+}
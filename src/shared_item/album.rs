@@ -28,47 +28,16 @@ impl AlbumData {
             upc: upc.to_owned(),
         }
     }
-}
-
-impl PartialEq for AlbumData {
-    fn eq(&self, other: &Self) -> bool {
-        // if we have the upc we can use it to determine if two albums are the
-        // same with relatively high confidence
-        if self.upc.len() > 0 {
-            if self.upc == other.upc {
-                return true;
-            }
-            return false;
-        }
-
-        // if the normalized names do not match, the albums are probably not the same
-        if self.norm_name != other.norm_name {
-            return false;
-        }
-
-        // about 90% of the songs of an album have to be the same, so we can consider two ablbums
-        // to also be the same
-        let req_song_match_count =
-            (std::cmp::max(self.songs.len(), other.songs.len()) as f64 * 0.9).ceil() as usize;
-        let mut song_match_count = 0;
 
-        for song in &self.songs {
-            if other.songs.contains(song) {
-                song_match_count += 1;
-            }
-        }
-
-        if song_match_count < req_song_match_count {
-            return false;
-        }
+    pub fn norm_name(&self) -> &str {
+        &self.norm_name
+    }
 
-        // as long as one artist of an album is the same
-        for artist in &self.artists {
-            if other.artists.contains(artist) {
-                return true;
-            }
-        }
+    pub fn songs(&self) -> &[SongData] {
+        &self.songs
+    }
 
-        return false;
+    pub fn artists(&self) -> &[ArtistData] {
+        &self.artists
     }
 }
@@ -24,6 +24,14 @@ impl ArtistData {
         }
     }
 
+    pub fn norm_name(&self) -> &str {
+        &self.norm_name
+    }
+
+    pub fn albums(&self) -> &[AlbumData] {
+        &self.albums
+    }
+
     pub fn add_album(&mut self, album: &AlbumData) {
         self.albums.push(album.clone())
     }
@@ -32,30 +40,3 @@ impl ArtistData {
         self.albums.extend_from_slice(albums);
     }
 }
-
-impl PartialEq for ArtistData {
-    fn eq(&self, other: &Self) -> bool {
-        // in case an artist has no albums, we have to compare by name
-        if self.albums.len() == 0 || other.albums.len() == 0 {
-            // if the display name or the normalized name matches, return true
-            if self.norm_name != other.norm_name {
-                return false;
-            }
-            return true;
-        }
-        let req_album_match_count =
-            (std::cmp::min(self.albums.len(), other.albums.len()) as f64 * 0.5).ceil() as usize;
-        let mut album_match_count = 0;
-
-        for album in &self.albums {
-            if other.albums.contains(&album) {
-                album_match_count += 1;
-                if album_match_count >= req_album_match_count {
-                    return true;
-                }
-            }
-        }
-
-        return false;
-    }
-}
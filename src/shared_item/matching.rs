@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::MatchingConfig;
+
+use super::{AlbumData, ArtistData, Data, SongData};
+
+/// The signals the match engine takes into account when comparing two items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Signal {
+    Isrc,
+    Upc,
+    Title,
+    ArtistOverlap,
+    DurationDelta,
+    TrackOverlap,
+    AlbumOverlap,
+}
+
+/// The contribution of a single signal. `score` is between 0 and 1, `weight` determines how much
+/// the signal counts towards the confidence.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Contribution {
+    pub signal: Signal,
+    pub score: f64,
+    pub weight: f64,
+}
+
+/// The result of comparing two items. Signals for which one of the items lacks the necessary
+/// information are left out, so they neither raise nor lower the confidence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchScore {
+    contributions: Vec<Contribution>,
+}
+
+impl MatchScore {
+    fn add(&mut self, signal: Signal, score: f64, weight: f64) {
+        self.contributions.push(Contribution {
+            signal,
+            score,
+            weight,
+        });
+    }
+
+    pub fn contributions(&self) -> &[Contribution] {
+        &self.contributions
+    }
+
+    pub fn contribution(&self, signal: Signal) -> Option<&Contribution> {
+        self.contributions
+            .iter()
+            .find(|contribution| contribution.signal == signal)
+    }
+
+    /// The weighted average of all contributions, between 0 and 1.
+    pub fn confidence(&self) -> f64 {
+        let total_weight: f64 = self.contributions.iter().map(|c| c.weight).sum();
+        if total_weight == 0.0 {
+            return 0.0;
+        }
+
+        self.contributions
+            .iter()
+            .map(|c| c.score * c.weight)
+            .sum::<f64>()
+            / total_weight
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    config: MatchingConfig,
+}
+
+impl Matcher {
+    // A matching ISRC or UPC is by far the strongest signal. A mismatch on the other hand is only
+    // weak evidence, since regional re-releases of the same recording get different identifiers.
+    const IDENTIFIER_WEIGHT: f64 = 20.0;
+    const IDENTIFIER_MISMATCH_WEIGHT: f64 = 1.0;
+    const TITLE_WEIGHT: f64 = 2.0;
+    const ARTIST_OVERLAP_WEIGHT: f64 = 3.0;
+    const DURATION_WEIGHT: f64 = 2.0;
+    const TRACK_OVERLAP_WEIGHT: f64 = 3.0;
+    const ALBUM_OVERLAP_WEIGHT: f64 = 4.0;
+
+    pub fn new(config: &MatchingConfig) -> Self {
+        Self {
+            config: config.clone(),
+        }
+    }
+
+    pub fn score_songs(&self, song: &SongData, other: &SongData) -> MatchScore {
+        let mut score = MatchScore::default();
+
+        score_identifier(&mut score, Signal::Isrc, &song.isrc, &other.isrc);
+        score.add(
+            Signal::Title,
+            equality(song.norm_name(), other.norm_name()),
+            Self::TITLE_WEIGHT,
+        );
+        if let Some(overlap) = artist_overlap(song.artists(), other.artists()) {
+            score.add(Signal::ArtistOverlap, overlap, Self::ARTIST_OVERLAP_WEIGHT);
+        }
+        // a duration of 0 means that the provider did not return one
+        if song.duration() > 0 && other.duration() > 0 {
+            let delta = song.duration().abs_diff(other.duration());
+            score.add(
+                Signal::DurationDelta,
+                self.duration_score(delta),
+                Self::DURATION_WEIGHT,
+            );
+        }
+
+        score
+    }
+
+    pub fn score_albums(&self, album: &AlbumData, other: &AlbumData) -> MatchScore {
+        let mut score = MatchScore::default();
+
+        score_identifier(&mut score, Signal::Upc, &album.upc, &other.upc);
+        score.add(
+            Signal::Title,
+            equality(album.norm_name(), other.norm_name()),
+            Self::TITLE_WEIGHT,
+        );
+        if let Some(overlap) = artist_overlap(album.artists(), other.artists()) {
+            score.add(Signal::ArtistOverlap, overlap, Self::ARTIST_OVERLAP_WEIGHT);
+        }
+        // search results and discographies usually do not contain the tracks of an album
+        if !album.songs().is_empty() && !other.songs().is_empty() {
+            let matched = album
+                .songs()
+                .iter()
+                .filter(|song| other.songs().iter().any(|o| self.songs_match(song, o)))
+                .count();
+            let overlap = matched as f64 / album.songs().len().max(other.songs().len()) as f64;
+            score.add(Signal::TrackOverlap, overlap, Self::TRACK_OVERLAP_WEIGHT);
+        }
+
+        score
+    }
+
+    pub fn score_artists(&self, artist: &ArtistData, other: &ArtistData) -> MatchScore {
+        let mut score = MatchScore::default();
+
+        score.add(
+            Signal::Title,
+            equality(artist.norm_name(), other.norm_name()),
+            Self::TITLE_WEIGHT,
+        );
+        // discographies are usually incomplete, so the overlap is measured against the smaller
+        // one
+        if !artist.albums().is_empty() && !other.albums().is_empty() {
+            let matched = artist
+                .albums()
+                .iter()
+                .filter(|album| other.albums().iter().any(|o| self.albums_match(album, o)))
+                .count();
+            let overlap = matched as f64 / artist.albums().len().min(other.albums().len()) as f64;
+            score.add(Signal::AlbumOverlap, overlap, Self::ALBUM_OVERLAP_WEIGHT);
+        }
+
+        score
+    }
+
+    /// Scores two items of the same type. Playlists and items of different types are not
+    /// comparable and get an empty score.
+    pub fn score_data(&self, data: &Data, other: &Data) -> MatchScore {
+        match (data, other) {
+            (Data::Song(song), Data::Song(other)) => self.score_songs(song, other),
+            (Data::Album(album), Data::Album(other)) => self.score_albums(album, other),
+            (Data::Artist(artist), Data::Artist(other)) => self.score_artists(artist, other),
+            _ => MatchScore::default(),
+        }
+    }
+
+    pub fn songs_match(&self, song: &SongData, other: &SongData) -> bool {
+        self.score_songs(song, other).confidence() >= self.config.song_threshold
+    }
+
+    pub fn albums_match(&self, album: &AlbumData, other: &AlbumData) -> bool {
+        self.score_albums(album, other).confidence() >= self.config.album_threshold
+    }
+
+    pub fn artists_match(&self, artist: &ArtistData, other: &ArtistData) -> bool {
+        self.score_artists(artist, other).confidence() >= self.config.artist_threshold
+    }
+
+    /// Durations within the tolerance score fully, beyond that the score drops linearly and
+    /// reaches 0 at three times the tolerance.
+    fn duration_score(&self, delta: u64) -> f64 {
+        let tolerance = self.config.duration_tolerance;
+        if delta <= tolerance {
+            return 1.0;
+        }
+        if tolerance == 0 {
+            return 0.0;
+        }
+
+        (1.0 - (delta - tolerance) as f64 / (2 * tolerance) as f64).max(0.0)
+    }
+}
+
+fn score_identifier(score: &mut MatchScore, signal: Signal, id: &str, other_id: &str) {
+    if id.is_empty() || other_id.is_empty() {
+        return;
+    }
+    if id == other_id {
+        score.add(signal, 1.0, Matcher::IDENTIFIER_WEIGHT);
+    } else {
+        score.add(signal, 0.0, Matcher::IDENTIFIER_MISMATCH_WEIGHT);
+    }
+}
+
+fn equality(name: &str, other_name: &str) -> f64 {
+    if !name.is_empty() && name == other_name {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// The share of artists of the smaller list that also appear in the other list. Providers differ
+/// in how they list multiple artists, e.g. "Beyoncé & JAY-Z" as one artist or as two separate
+/// ones, so the normalized names are split into the individual artists before comparing them.
+fn artist_overlap(artists: &[ArtistData], other_artists: &[ArtistData]) -> Option<f64> {
+    let split = |artists: &[ArtistData]| -> Vec<String> {
+        let mut names: Vec<String> = artists
+            .iter()
+            .flat_map(|artist| artist.norm_name().split(" and "))
+            .filter(|name| !name.is_empty())
+            .map(str::to_owned)
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    };
+
+    let names = split(artists);
+    let other_names = split(other_artists);
+    if names.is_empty() || other_names.is_empty() {
+        return None;
+    }
+
+    let shared = names
+        .iter()
+        .filter(|name| other_names.contains(name))
+        .count();
+    Some(shared as f64 / names.len().min(other_names.len()) as f64)
+}
@@ -1,16 +1,20 @@
 pub mod song;
 pub mod album;
 pub mod artist;
+pub mod matching;
 pub mod norm;
+pub mod playlist;
 
 pub use song::SongData;
 pub use album::AlbumData;
 pub use artist::ArtistData;
+pub use playlist::PlaylistData;
 
 pub enum Data {
     Song(SongData),
     Album(AlbumData),
     Artist(ArtistData),
+    Playlist(PlaylistData),
 }
 
 impl Data {
@@ -19,13 +23,15 @@ impl Data {
             Self::Song(_) => String::from("Song"),
             Self::Album(_) => String::from("Album"),
             Self::Artist(_) => String::from("Artist"),
+            Self::Playlist(_) => String::from("Playlist"),
         }
     }
     pub fn get_display_name(&self) -> String {
         match self {
             Self::Song(data) => data.display_name.clone(),
             Self::Album(data) => data.display_name.clone(),
-            Self::Artist(data) => data.display_name.clone()
+            Self::Artist(data) => data.display_name.clone(),
+            Self::Playlist(data) => data.display_name.clone(),
         }
     }
 }
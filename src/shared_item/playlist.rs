@@ -0,0 +1,27 @@
+use super::SongData;
+
+#[derive(Debug, Clone)]
+pub struct PlaylistData {
+    pub display_name: String,
+    pub songs: Vec<SongData>,
+    /// Whether the playlist has more songs than were fetched.
+    pub truncated: bool,
+}
+
+impl PlaylistData {
+    pub fn new(name: &str, songs: Vec<SongData>) -> Self {
+        Self {
+            display_name: name.to_owned(),
+            songs,
+            truncated: false,
+        }
+    }
+
+    /// Keeps only the first `max_songs` songs and marks the playlist as truncated, if it had more.
+    pub fn truncate(&mut self, max_songs: usize) {
+        if self.songs.len() > max_songs {
+            self.songs.truncate(max_songs);
+            self.truncated = true;
+        }
+    }
+}
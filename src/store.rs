@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use rusqlite::{Connection, OptionalExtension};
+use rust_iso3166::CountryCode;
+use thiserror::Error;
+
+use crate::share_link::{LinkType, ShareLink, ShareObject};
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("The database was created by a newer version (schema version {0}).")]
+    UnknownSchemaVersion(usize),
+}
+
+/// The schema migrations in the order they are applied. The schema version stored in the
+/// database is the number of applied migrations, so migrations must never be changed or
+/// reordered once released. Changes to the schema are made by appending a new one.
+const MIGRATIONS: &[&str] = &[
+    // 1: initial schema
+    "CREATE TABLE songs (
+        isrc TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        country TEXT NOT NULL,
+        id TEXT NOT NULL,
+        PRIMARY KEY (isrc, provider, country)
+    );
+    CREATE TABLE albums (
+        upc TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        country TEXT NOT NULL,
+        id TEXT NOT NULL,
+        PRIMARY KEY (upc, provider, country)
+    );",
+];
+
+/// Persistent mapping of ISRCs and UPCs to the IDs of songs and albums on the streaming services,
+/// so that identifiers only have to be looked up once. SQLite blocks while it reads or writes,
+/// so the queries run on the blocking threads of tokio.
+pub struct MappingStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl MappingStore {
+    /// Opens the database at the path and migrates it to the current schema. The path `:memory:`
+    /// opens a database that is not persisted.
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let mut connection = Connection::open(path)?;
+        migrate(&mut connection)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// The link to the item with the identifier on the streaming service, if it has been stored
+    /// before. Only songs and albums have identifiers.
+    pub async fn get_link(
+        &self,
+        share_obj: ShareObject,
+        identifier: &str,
+        link_type: LinkType,
+        country_code: &CountryCode,
+    ) -> Result<Option<ShareLink>, StoreError> {
+        let query = match share_obj {
+            ShareObject::Song => {
+                "SELECT id FROM songs WHERE isrc = ?1 AND provider = ?2 AND country = ?3"
+            }
+            ShareObject::Album => {
+                "SELECT id FROM albums WHERE upc = ?1 AND provider = ?2 AND country = ?3"
+            }
+            ShareObject::Artist | ShareObject::Playlist => return Ok(None),
+        };
+
+        let parameters = (
+            identifier.to_owned(),
+            link_type.to_string(),
+            country_code.alpha2,
+        );
+        let id: Option<String> = self
+            .run(move |connection| {
+                connection
+                    .query_row(query, parameters, |row| row.get(0))
+                    .optional()
+            })
+            .await?;
+
+        Ok(id.map(|id| ShareLink::new(link_type, share_obj, &id, country_code)))
+    }
+
+    /// Stores the identifier of the linked song or album. Other links are ignored.
+    pub async fn insert_link(&self, identifier: &str, link: &ShareLink) -> Result<(), StoreError> {
+        let query = match link.share_obj {
+            ShareObject::Song => {
+                "INSERT OR REPLACE INTO songs (isrc, provider, country, id) VALUES (?1, ?2, ?3, ?4)"
+            }
+            ShareObject::Album => {
+                "INSERT OR REPLACE INTO albums (upc, provider, country, id) VALUES (?1, ?2, ?3, ?4)"
+            }
+            ShareObject::Artist | ShareObject::Playlist => return Ok(()),
+        };
+        if identifier.is_empty() {
+            return Ok(());
+        }
+
+        let parameters = (
+            identifier.to_owned(),
+            link.link_type.to_string(),
+            link.country_code.alpha2,
+            link.id.clone(),
+        );
+        self.run(move |connection| connection.execute(query, parameters))
+            .await?;
+        Ok(())
+    }
+
+    /// Removes the stored link, e.g. because the streaming service no longer knows its ID.
+    pub async fn remove_link(&self, identifier: &str, link: &ShareLink) -> Result<(), StoreError> {
+        let query = match link.share_obj {
+            ShareObject::Song => {
+                "DELETE FROM songs WHERE isrc = ?1 AND provider = ?2 AND country = ?3 AND id = ?4"
+            }
+            ShareObject::Album => {
+                "DELETE FROM albums WHERE upc = ?1 AND provider = ?2 AND country = ?3 AND id = ?4"
+            }
+            ShareObject::Artist | ShareObject::Playlist => return Ok(()),
+        };
+
+        let parameters = (
+            identifier.to_owned(),
+            link.link_type.to_string(),
+            link.country_code.alpha2,
+            link.id.clone(),
+        );
+        self.run(move |connection| connection.execute(query, parameters))
+            .await?;
+        Ok(())
+    }
+
+    pub fn schema_version(&self) -> Result<usize, StoreError> {
+        Ok(schema_version(&self.connection.lock().unwrap())?)
+    }
+
+    /// Runs the query on a blocking thread, so that it does not hold up other tasks.
+    async fn run<T, F>(&self, query: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, rusqlite::Error> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        let result = tokio::task::spawn_blocking(move || query(&connection.lock().unwrap()))
+            .await
+            .expect("Database queries should never panic.");
+        Ok(result?)
+    }
+}
+
+fn schema_version(connection: &Connection) -> Result<usize, rusqlite::Error> {
+    connection.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Applies all migrations the database has not seen yet, each in its own transaction.
+fn migrate(connection: &mut Connection) -> Result<(), StoreError> {
+    let version = schema_version(connection)?;
+    if version > MIGRATIONS.len() {
+        return Err(StoreError::UnknownSchemaVersion(version));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::{
+            ConversionError, ErrorReason,
+            availability::{Availability, AvailabilityResults, check_availability},
+        },
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::Data,
+};
+use rust_iso3166::{CountryCode, from_alpha2};
+
+use common::{SingleSongProvider, api_clients};
+
+fn countries(alpha2: &[&str]) -> Vec<CountryCode> {
+    alpha2
+        .iter()
+        .map(|code| from_alpha2(code).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn availability_is_reported_per_provider_and_country() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider {
+        available_in: Some(vec!["DE", "US"]),
+        ..SingleSongProvider::new(LinkType::Tidal)
+    });
+    registry.register(SingleSongProvider {
+        available_in: Some(vec!["US"]),
+        ..SingleSongProvider::new(LinkType::Deezer)
+    });
+
+    let availability = check_availability(
+        "https://www.deezer.com/track/1",
+        &countries(&["DE", "US", "JP"]),
+        api_clients(registry),
+    )
+    .await
+    .unwrap();
+    let availability: AvailabilityResults = serde_json::from_str(&availability).unwrap();
+
+    let by_country = |available: &[(&str, bool)]| -> BTreeMap<String, bool> {
+        available
+            .iter()
+            .map(|(country, available)| (country.to_string(), *available))
+            .collect()
+    };
+    assert_eq!(
+        availability.results,
+        vec![
+            Availability {
+                provider: String::from("Tidal"),
+                url: String::from("https://tidal.com/browse/track/1"),
+                countries: by_country(&[("DE", true), ("JP", false), ("US", true)]),
+            },
+            Availability {
+                provider: String::from("Deezer"),
+                url: String::from("https://www.deezer.com/track/1"),
+                countries: by_country(&[("DE", false), ("JP", false), ("US", true)]),
+            },
+        ]
+    );
+    assert!(availability.errors.is_empty());
+}
+
+#[tokio::test]
+async fn providers_without_availability_information_are_reported() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider {
+        available_in: None,
+        ..SingleSongProvider::new(LinkType::AppleMusic)
+    });
+    registry.register(SingleSongProvider {
+        available_in: Some(vec!["DE"]),
+        ..SingleSongProvider::new(LinkType::Deezer)
+    });
+
+    let availability = check_availability(
+        "https://www.deezer.com/track/1",
+        &countries(&["DE"]),
+        api_clients(registry),
+    )
+    .await
+    .unwrap();
+    let availability: AvailabilityResults = serde_json::from_str(&availability).unwrap();
+
+    assert_eq!(availability.results.len(), 1);
+    assert_eq!(availability.errors.len(), 1);
+    assert_eq!(availability.errors[0].provider, "AppleMusic");
+    assert_eq!(availability.errors[0].reason, ErrorReason::Unsupported);
+}
+
+#[tokio::test]
+async fn playlists_are_rejected_without_fetching_them() {
+    struct PlaylistProvider(SingleSongProvider);
+
+    #[async_trait]
+    impl MusicProvider for PlaylistProvider {
+        fn info(&self) -> ProviderInfo {
+            ProviderInfo {
+                parses: &[ShareObject::Song, ShareObject::Playlist],
+                ..self.0.info()
+            }
+        }
+
+        async fn link_to_data(&self, _link: &ShareLink) -> Result<Data, ApiError> {
+            panic!("The playlist should not be fetched.")
+        }
+
+        async fn data_to_link(
+            &self,
+            data: &Data,
+            country_code: &CountryCode,
+        ) -> Result<ShareLink, ApiError> {
+            self.0.data_to_link(data, country_code).await
+        }
+
+        async fn get_artwork(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<String, ApiError> {
+            Ok(String::new())
+        }
+    }
+
+    let mut registry = ProviderRegistry::new();
+    registry.register(PlaylistProvider(SingleSongProvider::new(LinkType::Deezer)));
+
+    let result = check_availability(
+        "https://www.deezer.com/playlist/1",
+        &countries(&["DE"]),
+        api_clients(registry),
+    )
+    .await;
+    assert!(matches!(
+        result,
+        Err(ConversionError::ApiClient(ApiError::UnsupportedFeature))
+    ));
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use interlude::api::conversion::circuit_breaker::CircuitBreaker;
+
+#[test]
+fn opens_after_repeated_failures() {
+    let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+    breaker.record_failure();
+    breaker.record_failure();
+    assert!(breaker.allow_request());
+
+    breaker.record_failure();
+    assert!(!breaker.allow_request());
+    assert!(!breaker.is_available());
+}
+
+#[test]
+fn success_resets_the_failures() {
+    let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+    breaker.record_failure();
+    breaker.record_success();
+    breaker.record_failure();
+    assert!(breaker.allow_request());
+}
+
+#[test]
+fn lets_a_single_probe_through_after_the_cooldown() {
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+    breaker.record_failure();
+    assert!(!breaker.allow_request());
+
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(breaker.allow_request());
+    assert!(!breaker.allow_request());
+
+    breaker.record_success();
+    assert!(breaker.allow_request());
+    assert!(breaker.is_available());
+}
+
+#[test]
+fn failed_probe_opens_the_circuit_again() {
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(10));
+    breaker.record_failure();
+    std::thread::sleep(Duration::from_millis(20));
+    assert!(breaker.allow_request());
+
+    breaker.record_failure();
+    assert!(!breaker.allow_request());
+}
@@ -0,0 +1,120 @@
+// Test doubles shared by the integration tests, which convert links without reaching the
+// streaming services. Not every test uses all of them.
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::ApiClients,
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    config::Config,
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::{AlbumData, ArtistData, Data, SongData},
+};
+use rust_iso3166::CountryCode;
+
+/// A streaming service whose catalog consists of a single song, which is available in the given
+/// countries. Without countries it does not know where the song is available.
+pub struct SingleSongProvider {
+    pub link_type: LinkType,
+    pub url: &'static str,
+    pub available_in: Option<Vec<&'static str>>,
+}
+
+impl SingleSongProvider {
+    pub fn new(link_type: LinkType) -> Self {
+        Self {
+            link_type,
+            url: "https://example.com",
+            available_in: None,
+        }
+    }
+
+    pub fn song() -> SongData {
+        SongData::new(
+            "Hurt",
+            "USIR19400263",
+            218,
+            vec![AlbumData::with_limited_info("The Downward Spiral", "")],
+            vec![ArtistData::without_albums("Nine Inch Nails")],
+        )
+    }
+}
+
+#[async_trait]
+impl MusicProvider for SingleSongProvider {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            link_type: self.link_type,
+            url: self.url,
+            asset_name: "example",
+            parses: &[ShareObject::Song],
+            converts_to: &[ShareObject::Song, ShareObject::Album],
+        }
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Song => Ok(Data::Song(Self::song())),
+            _ => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        match data {
+            Data::Song(song_data) if song_data.isrc == Self::song().isrc => Ok(ShareLink::new(
+                self.link_type,
+                ShareObject::Song,
+                "1",
+                country_code,
+            )),
+            _ => Err(ApiError::UnsuccessfulConversion),
+        }
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Ok(String::new())
+    }
+
+    async fn available_countries(
+        &self,
+        _link: &ShareLink,
+        countries: &[CountryCode],
+    ) -> Result<Vec<CountryCode>, ApiError> {
+        let available_in = self
+            .available_in
+            .as_ref()
+            .ok_or(ApiError::UnsupportedFeature)?;
+        Ok(countries
+            .iter()
+            .filter(|country| available_in.contains(&country.alpha2))
+            .copied()
+            .collect())
+    }
+}
+
+pub fn api_clients(providers: ProviderRegistry) -> Arc<ApiClients> {
+    api_clients_in(providers, "US")
+}
+
+/// The link mappings are kept in memory, so that the tests do not share a database.
+pub fn api_clients_in(providers: ProviderRegistry, default_country: &str) -> Arc<ApiClients> {
+    let config = Config {
+        database_path: String::from(":memory:"),
+        default_country: String::from(default_country),
+        ..Config::default()
+    };
+    Arc::new(ApiClients::with_providers(providers, &config))
+}
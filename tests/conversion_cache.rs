@@ -0,0 +1,96 @@
+use std::{slice, time::Duration};
+
+use interlude::{
+    api::conversion::{ConversionResults, Link, cache::ConversionCache},
+    config::CacheConfig,
+    share_link::{LinkType, ShareLink, ShareObject},
+};
+use rust_iso3166::from_alpha2;
+
+fn song_link(link_type: LinkType, id: &str) -> ShareLink {
+    ShareLink::new(link_type, ShareObject::Song, id, &from_alpha2("DE").unwrap())
+}
+
+fn results() -> ConversionResults {
+    ConversionResults {
+        results: vec![Link {
+            provider: "Deezer".to_string(),
+            r#type: "Song".to_string(),
+            display_name: "Hurt".to_string(),
+            url: "https://www.deezer.com/track/1".to_string(),
+            ..Default::default()
+        }],
+        errors: Vec::new(),
+    }
+}
+
+#[test]
+fn cached_results_are_found_by_every_converted_link() {
+    let cache = ConversionCache::new(&CacheConfig::default());
+    let spotify = song_link(LinkType::Spotify, "5Aw7tCjLgKTAF1mRXQfVHm");
+    let deezer = song_link(LinkType::Deezer, "1");
+    cache.insert(&spotify, slice::from_ref(&deezer), &results());
+
+    assert_eq!(cache.get(&spotify).unwrap().results, results().results);
+    assert_eq!(cache.get(&deezer).unwrap().results, results().results);
+    assert!(cache.get(&song_link(LinkType::Tidal, "1")).is_none());
+}
+
+#[test]
+fn country_is_part_of_the_key() {
+    let cache = ConversionCache::new(&CacheConfig::default());
+    let german = song_link(LinkType::AppleMusic, "1");
+    let american = ShareLink::new(
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1",
+        &from_alpha2("US").unwrap(),
+    );
+    cache.insert(&german, &[], &results());
+
+    assert!(cache.get(&american).is_none());
+}
+
+#[test]
+fn least_recently_used_entries_are_evicted() {
+    let cache = ConversionCache::new(&CacheConfig {
+        capacity: 2,
+        ..CacheConfig::default()
+    });
+    let first = song_link(LinkType::Deezer, "1");
+    let second = song_link(LinkType::Deezer, "2");
+    let third = song_link(LinkType::Deezer, "3");
+    cache.insert(&first, &[], &results());
+    cache.insert(&second, &[], &results());
+    cache.get(&first);
+    cache.insert(&third, &[], &results());
+
+    assert!(cache.get(&first).is_some());
+    assert!(cache.get(&second).is_none());
+    assert!(cache.get(&third).is_some());
+}
+
+#[test]
+fn expired_entries_are_not_returned() {
+    let cache = ConversionCache::new(&CacheConfig {
+        ttl: 0,
+        ..CacheConfig::default()
+    });
+    let link = song_link(LinkType::Deezer, "1");
+    cache.insert(&link, &[], &results());
+    std::thread::sleep(Duration::from_millis(5));
+
+    assert!(cache.get(&link).is_none());
+}
+
+#[test]
+fn capacity_of_zero_disables_the_cache() {
+    let cache = ConversionCache::new(&CacheConfig {
+        capacity: 0,
+        ..CacheConfig::default()
+    });
+    let link = song_link(LinkType::Deezer, "1");
+    cache.insert(&link, &[], &results());
+
+    assert!(cache.get(&link).is_none());
+}
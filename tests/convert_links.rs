@@ -1,8 +1,12 @@
 use std::sync::Arc;
 
 use interlude::{
-    api::conversion::{convert, ApiClients, ConversionResults, Link},
+    api::{
+        conversion::{convert, ApiClients, AppLink, ConversionResults, ErrorReason, Link, MatchMethod},
+        ApiError,
+    },
     config::Config,
+    share_link::{LinkType, ShareLink, ShareObject},
 };
 use once_cell::sync::Lazy;
 use reqwest::Client;
@@ -12,12 +16,104 @@ static CLIENT: Lazy<Client> = Lazy::new(|| {
     Client::new()
 });
 
+/// The credentials from the config, with the link mappings kept in memory instead of the
+/// database the server uses.
+fn test_config() -> Config {
+    Config {
+        database_path: String::from(":memory:"),
+        ..toml::from_str(CONFIG).unwrap()
+    }
+}
+
+/// The confidence and identifiers depend on the provider's current metadata, so they are left out
+/// of the comparison, as are the app links, which only depend on the URL.
+fn without_scores(conversion: &ConversionResults) -> Vec<Link> {
+    conversion
+        .results
+        .iter()
+        .map(|link| Link {
+            confidence: 0.0,
+            identifiers: Default::default(),
+            app: None,
+            ..link.clone()
+        })
+        .collect()
+}
+
+#[test]
+fn links_without_match_details_deserialize() {
+    let json = r#"{"provider":"Deezer","type":"Song","displayName":"Hurt","url":"https://www.deezer.com/track/1","artwork":""}"#;
+    let link: Link = serde_json::from_str(json).unwrap();
+    assert!(!link.fuzzy_match);
+    assert_eq!(link.matched_by, None);
+}
+
+#[test]
+fn api_errors_are_reported_with_a_reason() {
+    assert_eq!(ErrorReason::from(&ApiError::UnsuccessfulConversion), ErrorReason::NotFound);
+    assert_eq!(ErrorReason::from(&ApiError::UnsupportedFeature), ErrorReason::Unsupported);
+    assert_eq!(ErrorReason::from(&ApiError::IncorrectAttributes), ErrorReason::ProviderError);
+    assert_eq!(serde_json::to_value(ErrorReason::RateLimited).unwrap(), "rateLimited");
+}
+
+#[test]
+fn results_without_errors_deserialize() {
+    let conversion: ConversionResults = serde_json::from_str(r#"{"results":[]}"#).unwrap();
+    assert!(conversion.errors.is_empty());
+}
+
+#[test]
+fn match_details_are_serialized() {
+    let link = Link {
+        provider: "Deezer".to_string(),
+        r#type: "Song".to_string(),
+        display_name: "Hurt".to_string(),
+        url: "https://www.deezer.com/track/1".to_string(),
+        confidence: 0.9,
+        matched_by: Some(MatchMethod::MetadataSearch),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::to_value(&link).unwrap();
+    assert_eq!(json["matchedBy"], "metadataSearch");
+    assert_eq!(json["confidence"], 0.9);
+    assert_eq!(json["displayName"], "Hurt");
+    assert!(json["identifiers"].as_object().unwrap().is_empty());
+}
+
+#[test]
+fn app_links_are_serialized() {
+    let link = ShareLink::new(LinkType::Tidal, ShareObject::Song, "1", &rust_iso3166::US);
+    let json = serde_json::to_value(AppLink::of(&link)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "uri": "tidal://track/1",
+            "androidPackage": "com.aspiro.tidal",
+            "androidIntent": "intent://track/1#Intent;scheme=tidal;package=com.aspiro.tidal;S.browser_fallback_url=https%3A%2F%2Ftidal.com%2Fbrowse%2Ftrack%2F1;end"
+        })
+    );
+
+    let link = ShareLink::new(LinkType::AppleMusic, ShareObject::Song, "1", &rust_iso3166::DE);
+    let app = AppLink::of(&link);
+    assert_eq!(app.uri, "music://music.apple.com/de/song/1");
+    assert_eq!(
+        app.android_intent,
+        "intent://music.apple.com/de/song/1#Intent;scheme=https;package=com.apple.android.music;S.browser_fallback_url=https%3A%2F%2Fmusic.apple.com%2Fde%2Fsong%2F1;end"
+    );
+
+    let link = Link {
+        provider: "Deezer".to_string(),
+        ..Default::default()
+    };
+    assert!(serde_json::to_value(&link).unwrap().get("app").is_none());
+}
+
 #[tokio::test]
 async fn convert_spotify_song_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.clone().credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     let url = "https://open.spotify.com/track/2HBBM75Xv3o2Mqdyh1NcM0?si=fb796f70fcb6449c";
@@ -28,34 +124,43 @@ async fn convert_spotify_song_link() {
       r#type: "Song".to_string(),
       display_name: "Heavy Is the Crown".to_string(),
       url: "https://open.spotify.com/track/5Aw7tCjLgKTAF1mRXQfVHm".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e02b11a5489e8cb11dd22b930a0".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e02b11a5489e8cb11dd22b930a0".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     }; 
     let tidal_result = Link {
       provider: "Tidal".to_string(),
       r#type: "Song".to_string(),
       display_name: "Heavy Is the Crown".to_string(),
       url: "https://tidal.com/browse/track/387265136".to_string(),
-      artwork: "https://resources.tidal.com/images/3f49a481/68e5/46e4/a57a/5da8a75aa106/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/3f49a481/68e5/46e4/a57a/5da8a75aa106/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
     let deezer_result = Link {
       provider: "Deezer".to_string(),
       r#type: "Song".to_string(),
       display_name: "Heavy Is the Crown".to_string(),
       url: "https://www.deezer.com/track/2994098971".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/1e8ffbd401303b5693226c12ee0b84fb/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/1e8ffbd401303b5693226c12ee0b84fb/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
 }
 
 #[tokio::test]
 async fn convert_spotify_album_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.clone().credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     println!("{:?}", &config.clone());
@@ -67,34 +172,43 @@ async fn convert_spotify_album_link() {
       r#type: "Album".to_string(),
       display_name: "Eternal Blue".to_string(),
       url: "https://open.spotify.com/album/4OXoBlapQygTdzAifJm8BL".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e023e234c82f96fa4ded8e5ca47".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e023e234c82f96fa4ded8e5ca47".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     }; 
     let tidal_result = Link  {
       provider: "Tidal".to_string(),
       r#type: "Album".to_string(),
       display_name: "Eternal Blue".to_string(),
       url: "https://tidal.com/browse/album/194372122".to_string(),
-      artwork: "https://resources.tidal.com/images/22968d83/ae3b/47fc/90db/a94fcd0036df/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/22968d83/ae3b/47fc/90db/a94fcd0036df/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
     let deezer_result = Link {
       provider: "Deezer".to_string(),
       r#type: "Album".to_string(),
       display_name: "Eternal Blue".to_string(),
       url: "https://www.deezer.com/album/252187122".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/e858dbae6f773cdb34f0c7fa47a526d8/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/e858dbae6f773cdb34f0c7fa47a526d8/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
 }
 
 #[tokio::test]
 async fn convert_tidal_song_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     let url = "https://tidal.com/browse/track/1885625/u";
@@ -105,34 +219,43 @@ async fn convert_tidal_song_link() {
       r#type: "Song".to_string(),
       display_name: "Snuff".to_string(),
       url: "https://open.spotify.com/track/0p6ZIbYw39oaAQX93tpETN".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e02457163bec7e8e4decf8c6375".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e02457163bec7e8e4decf8c6375".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     }; 
     let tidal_result = Link  {
       provider: "Tidal".to_string(),
       r#type: "Song".to_string(),
       display_name: "Snuff".to_string(),
       url: "https://tidal.com/browse/track/1885625".to_string(),
-      artwork: "https://resources.tidal.com/images/4f837fa6/edbc/41c0/8a7f/656cb3ddf004/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/4f837fa6/edbc/41c0/8a7f/656cb3ddf004/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
     let deezer_result = Link {
       provider: "Deezer".to_string(),
       r#type: "Song".to_string(),
       display_name: "Snuff".to_string(),
       url: "https://www.deezer.com/track/1195567".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/3d4d0fe601be67cb2e13654d40d7101a/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/3d4d0fe601be67cb2e13654d40d7101a/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
 }
 
 #[tokio::test]
 async fn convert_tidal_album_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     let url = "https://tidal.com/browse/album/1885614/u";
@@ -143,34 +266,43 @@ async fn convert_tidal_album_link() {
       r#type: "Album".to_string(),
       display_name: "All Hope Is Gone".to_string(),
       url: "https://open.spotify.com/album/0hFWapnP7orzXCMwNU5DuA".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e02457163bec7e8e4decf8c6375".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e02457163bec7e8e4decf8c6375".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     }; 
     let tidal_result = Link  {
       provider: "Tidal".to_string(),
       r#type: "Album".to_string(),
       display_name: "All Hope Is Gone".to_string(),
       url: "https://tidal.com/browse/album/1885614".to_string(),
-      artwork: "https://resources.tidal.com/images/4f837fa6/edbc/41c0/8a7f/656cb3ddf004/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/4f837fa6/edbc/41c0/8a7f/656cb3ddf004/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
     let deezer_result = Link  {
       provider: "Deezer".to_string(),
       r#type: "Album".to_string(),
       display_name: "All Hope Is Gone".to_string(),
       url: "https://www.deezer.com/album/127402".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/3d4d0fe601be67cb2e13654d40d7101a/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/3d4d0fe601be67cb2e13654d40d7101a/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
 }
 
 #[tokio::test]
 async fn convert_deezer_song_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     let url = "https://link.deezer.com/s/30X12yMuBSBgGoX01n05M";
@@ -181,34 +313,43 @@ async fn convert_deezer_song_link() {
       r#type: "Song".to_string(),
       display_name: "Look To Windward".to_string(),
       url: "https://open.spotify.com/track/4Lojbtk7XNMdSKRHSFbdkm".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e020e48dcb579fd8e59d0a3c218".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e020e48dcb579fd8e59d0a3c218".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     }; 
     let tidal_result = Link  {
       provider: "Tidal".to_string(),
       r#type: "Song".to_string(),
       display_name: "Look To Windward".to_string(),
       url: "https://tidal.com/browse/track/434030392".to_string(),
-      artwork: "https://resources.tidal.com/images/5e7d37ee/9c40/4388/9e51/4d49e67b4310/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/5e7d37ee/9c40/4388/9e51/4d49e67b4310/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
     let deezer_result = Link  {
       provider: "Deezer".to_string(),
       r#type: "Song".to_string(),
       display_name: "Look To Windward".to_string(),
       url: "https://www.deezer.com/track/3330723931".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/88cb1ee2758133c9dd4514deea199c0b/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/88cb1ee2758133c9dd4514deea199c0b/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Isrc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
 }
 
 #[tokio::test]
 async fn convert_deezer_album_link() {
-    let config: Config = toml::from_str(CONFIG).unwrap();
+    let config = test_config();
     let client = CLIENT.clone();
     let api_clients = Arc::new(
-        ApiClients::new(&client, config.credentials.expect("No credentials found.")).await,
+        ApiClients::new(&client, &config).await,
     );
 
     let url = "https://link.deezer.com/s/30X1h4uTFd7R8JUxoPuJV";
@@ -219,24 +360,56 @@ async fn convert_deezer_album_link() {
       r#type: "Album".to_string(),
       display_name: "Even In Arcadia".to_string(),
       url: "https://open.spotify.com/album/1lS7FeRcSUuIGqyg99UGpj".to_string(),
-      artwork: "https://i.scdn.co/image/ab67616d00001e020e48dcb579fd8e59d0a3c218".to_string()
+      artwork: "https://i.scdn.co/image/ab67616d00001e020e48dcb579fd8e59d0a3c218".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     }; 
     let tidal_result = Link   {
       provider: "Tidal".to_string(),
       r#type: "Album".to_string(),
       display_name: "Even In Arcadia".to_string(),
       url: "https://tidal.com/browse/album/434030391".to_string(),
-      artwork: "https://resources.tidal.com/images/5e7d37ee/9c40/4388/9e51/4d49e67b4310/320x320.jpg".to_string()
+      artwork: "https://resources.tidal.com/images/5e7d37ee/9c40/4388/9e51/4d49e67b4310/320x320.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
     let deezer_result = Link   {
       provider: "Deezer".to_string(),
       r#type: "Album".to_string(),
       display_name: "Even In Arcadia".to_string(),
       url: "https://www.deezer.com/album/744506781".to_string(),
-      artwork: "https://cdn-images.dzcdn.net/images/cover/88cb1ee2758133c9dd4514deea199c0b/250x250-000000-80-0-0.jpg".to_string()
+      artwork: "https://cdn-images.dzcdn.net/images/cover/88cb1ee2758133c9dd4514deea199c0b/250x250-000000-80-0-0.jpg".to_string(),
+      fuzzy_match: false,
+      matched_by: Some(MatchMethod::Upc),
+      ..Default::default()
     };
 
-    assert_eq!(conversion.results.contains(&spotify_result), true);
-    assert_eq!(conversion.results.contains(&tidal_result), true);
-    assert_eq!(conversion.results.contains(&deezer_result), true);
+    assert_eq!(without_scores(&conversion).contains(&spotify_result), true);
+    assert_eq!(without_scores(&conversion).contains(&tidal_result), true);
+    assert_eq!(without_scores(&conversion).contains(&deezer_result), true);
+}
+
+#[tokio::test]
+async fn convert_deezer_artist_link() {
+    let config = test_config();
+    let client = CLIENT.clone();
+    let api_clients = Arc::new(
+        ApiClients::new(&client, &config).await,
+    );
+
+    let url = "https://www.deezer.com/artist/27";
+
+    let conversion: ConversionResults = serde_json::from_str(&convert(url, api_clients).await.unwrap()).unwrap();
+    // the artwork of artists changes too often to compare it
+    let urls: Vec<(&str, &str)> = conversion
+        .results
+        .iter()
+        .map(|link| (link.provider.as_str(), link.url.as_str()))
+        .collect();
+
+    assert!(urls.contains(&("Spotify", "https://open.spotify.com/artist/4tZwfgrHOc3mvqYlEYSvVi")));
+    assert!(urls.contains(&("Deezer", "https://www.deezer.com/artist/27")));
+    assert!(conversion.results.iter().all(|link| link.r#type == "Artist" && link.matched_by == Some(MatchMethod::MetadataSearch)));
 }
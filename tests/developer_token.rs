@@ -0,0 +1,31 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use interlude::{api::authorization::DeveloperToken, config::AppleMusicCredentials};
+
+fn credentials(private_key_path: &str) -> AppleMusicCredentials {
+    AppleMusicCredentials {
+        team_id: String::from("TEAMID1234"),
+        key_id: String::from("KEYID12345"),
+        private_key_path: private_key_path.to_owned(),
+    }
+}
+
+#[test]
+fn sign_developer_token() {
+    let token = DeveloperToken::new(&credentials("tests/fixtures/AuthKey_TEST.p8")).unwrap();
+    assert!(!token.is_expired());
+
+    let mut parts = token.token.split('.');
+    let header: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts.next().unwrap()).unwrap()).unwrap();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts.next().unwrap()).unwrap()).unwrap();
+
+    assert_eq!(header["alg"], "ES256");
+    assert_eq!(header["kid"], "KEYID12345");
+    assert_eq!(claims["iss"], "TEAMID1234");
+}
+
+#[test]
+fn missing_private_key() {
+    assert!(DeveloperToken::new(&credentials("tests/fixtures/does_not_exist.p8")).is_err());
+}
@@ -0,0 +1,57 @@
+use hyper::StatusCode;
+use interlude::{
+    api::{ApiError, conversion::ConversionError},
+    server::error_response::ErrorResponse,
+    share_link::ShareLinkError,
+};
+
+#[test]
+fn user_errors_are_client_errors() {
+    let response = ErrorResponse::from(&ConversionError::Link(ShareLinkError::NotAShareLink));
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.code, "notAShareLink");
+
+    let response = ErrorResponse::from(&ConversionError::Link(ShareLinkError::InvalidUrl));
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.code, "invalidUrl");
+}
+
+#[test]
+fn provider_errors_map_to_statuses() {
+    let cases = [
+        (ApiError::UnsuccessfulConversion, StatusCode::NOT_FOUND, "notFound"),
+        (ApiError::UnsupportedFeature, StatusCode::UNPROCESSABLE_ENTITY, "unsupported"),
+        (ApiError::IncorrectAttributes, StatusCode::BAD_GATEWAY, "providerError"),
+        (ApiError::UnexpectedStatus(500), StatusCode::BAD_GATEWAY, "providerError"),
+        (ApiError::UnexpectedStatus(404), StatusCode::NOT_FOUND, "notFound"),
+        (ApiError::Unauthorized, StatusCode::SERVICE_UNAVAILABLE, "providerAuthFailure"),
+        (
+            ApiError::RateLimited { retry_after: None },
+            StatusCode::TOO_MANY_REQUESTS,
+            "rateLimited",
+        ),
+    ];
+    for (err, status, code) in cases {
+        let response = ErrorResponse::from(&ConversionError::ApiClient(err));
+        assert_eq!(response.status, status);
+        assert_eq!(response.code, code);
+    }
+}
+
+#[test]
+fn error_envelope_is_serialized() {
+    let response = ErrorResponse::from(&ShareLinkError::MalformedOrInvalidLink);
+    let json: serde_json::Value = serde_json::from_str(&response.to_json()).unwrap();
+    assert_eq!(json["code"], "malformedShareLink");
+    assert_eq!(json["message"], ShareLinkError::MalformedOrInvalidLink.to_string());
+    assert!(json.get("status").is_none());
+    assert!(json.get("details").is_none());
+}
+
+#[test]
+fn retry_after_is_passed_on() {
+    let response = ErrorResponse::from(&ApiError::RateLimited {
+        retry_after: Some(30),
+    });
+    assert_eq!(response.details.unwrap()["retryAfter"], 30);
+}
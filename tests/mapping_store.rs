@@ -0,0 +1,169 @@
+mod common;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::{ApiClients, ConversionResults, convert},
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    config::Config,
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::Data,
+    store::MappingStore,
+};
+use rust_iso3166::{CountryCode, from_alpha2};
+
+use common::SingleSongProvider;
+
+#[tokio::test]
+async fn stored_links_are_found_by_identifier() {
+    let store = MappingStore::open(":memory:").unwrap();
+    let germany = from_alpha2("DE").unwrap();
+    let link = ShareLink::new(LinkType::Deezer, ShareObject::Song, "2994098971", &germany);
+    store.insert_link("USWB12403465", &link).await.unwrap();
+
+    let found = store
+        .get_link(ShareObject::Song, "USWB12403465", LinkType::Deezer, &germany)
+        .await
+        .unwrap();
+    assert_eq!(found, Some(link));
+    let other_provider = store
+        .get_link(ShareObject::Song, "USWB12403465", LinkType::Tidal, &germany)
+        .await
+        .unwrap();
+    assert_eq!(other_provider, None);
+    let album = store
+        .get_link(ShareObject::Album, "USWB12403465", LinkType::Deezer, &germany)
+        .await
+        .unwrap();
+    assert_eq!(album, None);
+}
+
+#[tokio::test]
+async fn links_without_identifier_are_not_stored() {
+    let store = MappingStore::open(":memory:").unwrap();
+    let germany = from_alpha2("DE").unwrap();
+    let artist = ShareLink::new(LinkType::Spotify, ShareObject::Artist, "6XyY86QOPPrYVGvF9ch6wz", &germany);
+    store.insert_link("", &artist).await.unwrap();
+
+    let found = store
+        .get_link(ShareObject::Artist, "", LinkType::Spotify, &germany)
+        .await
+        .unwrap();
+    assert_eq!(found, None);
+}
+
+#[tokio::test]
+async fn database_is_migrated_once_and_persisted() {
+    let path = std::env::temp_dir().join(format!("interlude-store-{}.sqlite3", std::process::id()));
+    let path = path.to_str().unwrap();
+    let germany = from_alpha2("DE").unwrap();
+    let link = ShareLink::new(LinkType::Tidal, ShareObject::Album, "55391786", &germany);
+
+    {
+        let store = MappingStore::open(path).unwrap();
+        store.insert_link("5099902987620", &link).await.unwrap();
+    }
+    let store = MappingStore::open(path).unwrap();
+    assert_eq!(store.schema_version().unwrap(), 1);
+    let found = store
+        .get_link(ShareObject::Album, "5099902987620", LinkType::Tidal, &germany)
+        .await
+        .unwrap();
+    assert_eq!(found, Some(link));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn removed_links_are_no_longer_found() {
+    let store = MappingStore::open(":memory:").unwrap();
+    let germany = from_alpha2("DE").unwrap();
+    let link = ShareLink::new(LinkType::Deezer, ShareObject::Song, "2994098971", &germany);
+    store.insert_link("USWB12403465", &link).await.unwrap();
+
+    store.remove_link("USWB12403465", &link).await.unwrap();
+    let found = store
+        .get_link(ShareObject::Song, "USWB12403465", LinkType::Deezer, &germany)
+        .await
+        .unwrap();
+    assert_eq!(found, None);
+}
+
+/// Has given the song a new ID, so that only the new one can be fetched.
+struct ReleasedAgainProvider(SingleSongProvider);
+
+#[async_trait]
+impl MusicProvider for ReleasedAgainProvider {
+    fn info(&self) -> ProviderInfo {
+        self.0.info()
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.id.as_str() {
+            "2" => Ok(Data::Song(SingleSongProvider::song())),
+            _ => Err(ApiError::UnsuccessfulConversion),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        _data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        Ok(ShareLink::new(
+            self.0.link_type,
+            ShareObject::Song,
+            "2",
+            country_code,
+        ))
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Ok(String::new())
+    }
+}
+
+#[tokio::test]
+async fn stale_links_are_looked_up_again() {
+    let path = std::env::temp_dir().join(format!("interlude-stale-{}.sqlite3", std::process::id()));
+    let path = path.to_str().unwrap();
+    let us = from_alpha2("US").unwrap();
+    let isrc = SingleSongProvider::song().isrc;
+    let stale = ShareLink::new(LinkType::Tidal, ShareObject::Song, "1", &us);
+    MappingStore::open(path)
+        .unwrap()
+        .insert_link(&isrc, &stale)
+        .await
+        .unwrap();
+
+    let mut registry = ProviderRegistry::new();
+    registry.register(ReleasedAgainProvider(SingleSongProvider::new(LinkType::Tidal)));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    let config = Config {
+        database_path: path.to_owned(),
+        ..Config::default()
+    };
+    let api_clients = Arc::new(ApiClients::with_providers(registry, &config));
+    let conversion = convert("https://www.deezer.com/track/1", api_clients)
+        .await
+        .unwrap();
+    let conversion: ConversionResults = serde_json::from_str(&conversion).unwrap();
+
+    assert_eq!(conversion.results[0].url, "https://tidal.com/browse/track/2");
+    let found = MappingStore::open(path)
+        .unwrap()
+        .get_link(ShareObject::Song, &isrc, LinkType::Tidal, &us)
+        .await
+        .unwrap();
+    assert_eq!(found.map(|link| link.id), Some(String::from("2")));
+
+    std::fs::remove_file(path).unwrap();
+}
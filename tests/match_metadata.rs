@@ -0,0 +1,170 @@
+use interlude::{
+    config::MatchingConfig,
+    shared_item::{
+        AlbumData, ArtistData, SongData,
+        matching::{Matcher, Signal},
+    },
+};
+
+fn song(name: &str, isrc: &str, duration: u64, artists: &[&str]) -> SongData {
+    SongData::new(
+        name,
+        isrc,
+        duration,
+        Vec::new(),
+        artists
+            .iter()
+            .map(|artist| ArtistData::without_albums(artist))
+            .collect(),
+    )
+}
+
+fn matcher() -> Matcher {
+    Matcher::new(&MatchingConfig::default())
+}
+
+#[test]
+fn songs_match_by_metadata() {
+    let original = song("Blinding Lights", "", 200, &["The Weeknd"]);
+    let re_release = song("Blinding Lights (Remastered 2021)", "", 201, &["The Weeknd"]);
+    assert!(matcher().songs_match(&original, &re_release));
+}
+
+#[test]
+fn songs_match_with_differently_listed_artists() {
+    let joined = song("Crazy in Love", "", 236, &["Beyoncé & JAY-Z"]);
+    let separate = song("Crazy In Love", "", 235, &["Beyonce", "Jay Z"]);
+    assert!(matcher().songs_match(&joined, &separate));
+}
+
+#[test]
+fn songs_do_not_match_outside_duration_tolerance() {
+    let original = song("Heavy Is the Crown", "", 170, &["Linkin Park"]);
+    let live = song("Heavy Is the Crown", "", 190, &["Linkin Park"]);
+    assert!(!matcher().songs_match(&original, &live));
+}
+
+#[test]
+fn songs_do_not_match_with_different_artists() {
+    let original = song("Hurt", "", 218, &["Nine Inch Nails"]);
+    let cover = song("Hurt", "", 218, &["Johnny Cash"]);
+    assert!(!matcher().songs_match(&original, &cover));
+}
+
+#[test]
+fn songs_match_by_isrc_despite_differing_metadata() {
+    let original = song("Bohemian Rhapsody", "GBUM71029604", 354, &["Queen"]);
+    let localized = song("ボヘミアン・ラプソディ", "GBUM71029604", 355, &["クイーン"]);
+    let score = matcher().score_songs(&original, &localized);
+    assert_eq!(score.contribution(Signal::Isrc).unwrap().score, 1.0);
+    assert!(score.confidence() >= MatchingConfig::default().song_threshold);
+}
+
+#[test]
+fn very_short_songs_are_scored() {
+    let original = song("Intro", "", 1, &["The xx"]);
+    let other = song("Intro", "", 5, &["The xx"]);
+    let score = matcher().score_songs(&original, &other);
+    assert!(score.contribution(Signal::DurationDelta).unwrap().score < 1.0);
+    assert!(score.confidence() > 0.0);
+}
+
+#[test]
+fn missing_signals_do_not_count() {
+    let original = song("Hurt", "", 0, &["Nine Inch Nails"]);
+    let other = song("Hurt", "", 218, &["Nine Inch Nails"]);
+    let score = matcher().score_songs(&original, &other);
+    assert!(score.contribution(Signal::DurationDelta).is_none());
+    assert!(score.contribution(Signal::Isrc).is_none());
+    assert_eq!(score.confidence(), 1.0);
+}
+
+#[test]
+fn thresholds_are_configurable() {
+    let original = song("Heavy Is the Crown", "", 170, &["Linkin Park"]);
+    let live = song("Heavy Is the Crown", "", 190, &["Linkin Park"]);
+    let lenient = Matcher::new(&MatchingConfig {
+        song_threshold: 0.5,
+        ..MatchingConfig::default()
+    });
+    assert!(lenient.songs_match(&original, &live));
+
+    let tolerant = Matcher::new(&MatchingConfig {
+        duration_tolerance: 20,
+        ..MatchingConfig::default()
+    });
+    assert!(tolerant.songs_match(&original, &live));
+}
+
+#[test]
+fn albums_match_by_metadata() {
+    let original = AlbumData::new(
+        "Back to Black",
+        "0602517341297",
+        Vec::new(),
+        vec![ArtistData::without_albums("Amy Winehouse")],
+    );
+    let deluxe = AlbumData::new(
+        "Back to Black (Deluxe Edition)",
+        "0602517477552",
+        Vec::new(),
+        vec![ArtistData::without_albums("Amy Winehouse")],
+    );
+    assert!(matcher().albums_match(&original, &deluxe));
+}
+
+#[test]
+fn albums_do_not_match_without_shared_artist() {
+    let original = AlbumData::new(
+        "Greatest Hits",
+        "",
+        Vec::new(),
+        vec![ArtistData::without_albums("Queen")],
+    );
+    let other = AlbumData::new(
+        "Greatest Hits",
+        "",
+        Vec::new(),
+        vec![ArtistData::without_albums("ABBA")],
+    );
+    assert!(!matcher().albums_match(&original, &other));
+}
+
+fn artist(name: &str, albums: &[&str]) -> ArtistData {
+    ArtistData::new(
+        name,
+        albums
+            .iter()
+            .map(|album| {
+                AlbumData::new(
+                    album,
+                    "",
+                    Vec::new(),
+                    vec![ArtistData::without_albums(name)],
+                )
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn artists_match_by_their_discography() {
+    let original = artist("Nirvana", &["Bleach", "Nevermind", "In Utero"]);
+    // discographies differ between streaming services, e.g. by deluxe editions
+    let other = artist(
+        "Nirvana",
+        &[
+            "Nevermind (Remastered)",
+            "In Utero",
+            "MTV Unplugged in New York",
+        ],
+    );
+    assert!(matcher().artists_match(&original, &other));
+}
+
+#[test]
+fn artists_with_the_same_name_do_not_match_without_shared_albums() {
+    let grunge = artist("Nirvana", &["Bleach", "Nevermind", "In Utero"]);
+    let sixties = artist("Nirvana", &["The Story of Simon Simopath", "All of Us"]);
+    assert!(!matcher().artists_match(&grunge, &sixties));
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::convert,
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::Data,
+};
+use rust_iso3166::CountryCode;
+use serde_json::{Value, json};
+
+use common::{SingleSongProvider, api_clients};
+
+/// Does not know the ISRC of the song, but finds it by its title and artist.
+struct SearchOnlyProvider(SingleSongProvider);
+
+#[async_trait]
+impl MusicProvider for SearchOnlyProvider {
+    fn info(&self) -> ProviderInfo {
+        self.0.info()
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        self.0.link_to_data(link).await
+    }
+
+    async fn data_to_link(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        Err(ApiError::UnsuccessfulConversion)
+    }
+
+    async fn search_data_to_link(
+        &self,
+        _data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        Ok(ShareLink::new(
+            self.0.link_type,
+            ShareObject::Song,
+            "2",
+            country_code,
+        ))
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Ok(String::new())
+    }
+}
+
+#[tokio::test]
+async fn songs_without_known_isrc_are_found_by_their_metadata() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SearchOnlyProvider(SingleSongProvider::new(LinkType::Tidal)));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+
+    let conversion = convert("https://www.deezer.com/track/1", api_clients(registry))
+        .await
+        .unwrap();
+    let conversion: Value = serde_json::from_str(&conversion).unwrap();
+
+    let match_details: Vec<Value> = conversion["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|link| {
+            json!({
+                "provider": link["provider"],
+                "url": link["url"],
+                "matchedBy": link["matchedBy"],
+                "fuzzyMatch": link["fuzzyMatch"],
+            })
+        })
+        .collect();
+    assert_eq!(
+        match_details,
+        vec![
+            json!({
+                "provider": "Tidal",
+                "url": "https://tidal.com/browse/track/2",
+                "matchedBy": "metadataSearch",
+                "fuzzyMatch": true,
+            }),
+            json!({
+                "provider": "Deezer",
+                "url": "https://www.deezer.com/track/1",
+                "matchedBy": "isrc",
+                "fuzzyMatch": false,
+            }),
+        ]
+    );
+}
@@ -1,4 +1,4 @@
-use interlude::share_link::{LinkType, ShareLink};
+use interlude::share_link::{LINK_FORMATS, LinkType, ShareLink, ShareLinkError, ShareObject};
 
 #[tokio::test]
 async fn parse_spotify_song_link() {
@@ -109,3 +109,484 @@ async fn parse_apple_artist_link() {
     };
     assert_eq!(expected_result, ShareLink::from_url(url).await.unwrap());
 }
+
+#[tokio::test]
+async fn apple_song_link_to_url() {
+    let url = "https://music.apple.com/us/song/what-was-that/1810905307";
+    let share_link = ShareLink::from_url(url).await.unwrap();
+    assert_eq!(
+        "https://music.apple.com/us/song/1810905307",
+        share_link.to_url()
+    );
+}
+
+#[tokio::test]
+async fn parse_spotify_playlist_link() {
+    let url = "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M?si=1a2b3c4d5e6f4a7b";
+    let expected_result = ShareLink {
+        link_type: LinkType::Spotify,
+        country_code: rust_iso3166::from_alpha2("US").unwrap(),
+        share_obj: interlude::share_link::ShareObject::Playlist,
+        id: String::from("37i9dQZF1DXcBWIGoYBM5M"),
+    };
+    assert_eq!(expected_result, ShareLink::from_url(url).await.unwrap());
+}
+
+#[tokio::test]
+async fn parse_tidal_playlist_link() {
+    let url = "https://tidal.com/browse/playlist/36ea71a8-445e-41a4-82ab-6628c581535d?u";
+    let expected_result = ShareLink {
+        link_type: LinkType::Tidal,
+        country_code: rust_iso3166::from_alpha2("US").unwrap(),
+        share_obj: interlude::share_link::ShareObject::Playlist,
+        id: String::from("36ea71a8-445e-41a4-82ab-6628c581535d"),
+    };
+    assert_eq!(expected_result, ShareLink::from_url(url).await.unwrap());
+}
+
+#[tokio::test]
+async fn parse_deezer_playlist_link() {
+    let url = "https://www.deezer.com/de/playlist/1313621735";
+    let expected_result = ShareLink {
+        link_type: LinkType::Deezer,
+        country_code: rust_iso3166::from_alpha2("DE").unwrap(),
+        share_obj: interlude::share_link::ShareObject::Playlist,
+        id: String::from("1313621735"),
+    };
+    assert_eq!(expected_result, ShareLink::from_url(url).await.unwrap());
+}
+
+#[tokio::test]
+async fn links_without_country_get_the_default_country() {
+    let japan = rust_iso3166::from_alpha2("JP").unwrap();
+
+    let link = ShareLink::from_url_with_default_country("https://tidal.com/browse/track/1", &japan)
+        .await
+        .unwrap();
+    assert_eq!(link.country_code, japan);
+
+    let url = "https://open.spotify.com/intl-de/track/36puuD04lEUD8kVwQsTLm6";
+    let link = ShareLink::from_url_with_default_country(url, &japan)
+        .await
+        .unwrap();
+    assert_eq!(link.country_code.alpha2, "DE");
+}
+
+#[test]
+fn parse_provider_names() {
+    assert_eq!(LinkType::from_name("tidal"), Some(LinkType::Tidal));
+    assert_eq!(LinkType::from_name("Deezer"), Some(LinkType::Deezer));
+    assert_eq!(
+        LinkType::from_name("apple_music"),
+        Some(LinkType::AppleMusic)
+    );
+    assert_eq!(
+        LinkType::from_name("apple-music"),
+        Some(LinkType::AppleMusic)
+    );
+    assert_eq!(LinkType::from_name("napster"), None);
+}
+
+/// Real-world share links and what they are parsed into: the link type, kind, ID and country.
+const SHARE_LINKS: &[(&str, LinkType, ShareObject, &str, &str)] = &[
+    // Spotify
+    (
+        "https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    (
+        "http://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    (
+        "HTTPS://OPEN.SPOTIFY.COM/track/36puuD04lEUD8kVwQsTLm6",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    (
+        "https://open.spotify.com/embed/track/36puuD04lEUD8kVwQsTLm6?utm_source=generator",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    (
+        "https://open.spotify.com/embed/album/4LH4d3cOWNNsVw41Gqt2kv",
+        LinkType::Spotify,
+        ShareObject::Album,
+        "4LH4d3cOWNNsVw41Gqt2kv",
+        "US",
+    ),
+    (
+        "https://open.spotify.com/intl-ja/album/4LH4d3cOWNNsVw41Gqt2kv",
+        LinkType::Spotify,
+        ShareObject::Album,
+        "4LH4d3cOWNNsVw41Gqt2kv",
+        "US",
+    ),
+    (
+        "https://open.spotify.com/intl-pt_br/artist/0oSGxfWSnnOXhD2fKuz2Gy",
+        LinkType::Spotify,
+        ShareObject::Artist,
+        "0oSGxfWSnnOXhD2fKuz2Gy",
+        "BR",
+    ),
+    (
+        "https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6#comments",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    (
+        "  https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6/  ",
+        LinkType::Spotify,
+        ShareObject::Song,
+        "36puuD04lEUD8kVwQsTLm6",
+        "US",
+    ),
+    // Tidal
+    (
+        "https://tidal.com/track/300807510",
+        LinkType::Tidal,
+        ShareObject::Song,
+        "300807510",
+        "US",
+    ),
+    (
+        "https://listen.tidal.com/album/412502324",
+        LinkType::Tidal,
+        ShareObject::Album,
+        "412502324",
+        "US",
+    ),
+    (
+        "https://www.tidal.com/browse/track/300807510?u",
+        LinkType::Tidal,
+        ShareObject::Song,
+        "300807510",
+        "US",
+    ),
+    (
+        "https://tidal.com/browse/album/1885614/u",
+        LinkType::Tidal,
+        ShareObject::Album,
+        "1885614",
+        "US",
+    ),
+    (
+        "http://tidal.com/browse/artist/5036395#top",
+        LinkType::Tidal,
+        ShareObject::Artist,
+        "5036395",
+        "US",
+    ),
+    // Deezer
+    (
+        "https://www.deezer.com/en/track/3135556",
+        LinkType::Deezer,
+        ShareObject::Song,
+        "3135556",
+        "US",
+    ),
+    (
+        "https://www.deezer.com/fr/album/302127",
+        LinkType::Deezer,
+        ShareObject::Album,
+        "302127",
+        "FR",
+    ),
+    (
+        "https://m.deezer.com/track/3135556",
+        LinkType::Deezer,
+        ShareObject::Song,
+        "3135556",
+        "US",
+    ),
+    (
+        "https://deezer.com/us/artist/27",
+        LinkType::Deezer,
+        ShareObject::Artist,
+        "27",
+        "US",
+    ),
+    (
+        "https://www.deezer.com/en-gb/track/3135556?host=0&utm_campaign=clipboard",
+        LinkType::Deezer,
+        ShareObject::Song,
+        "3135556",
+        "GB",
+    ),
+    (
+        "https://www.deezer.com/track/3135556#lyrics",
+        LinkType::Deezer,
+        ShareObject::Song,
+        "3135556",
+        "US",
+    ),
+    // Apple Music
+    (
+        "https://music.apple.com/de/album/the-dark-side-of-the-moon/1065973699",
+        LinkType::AppleMusic,
+        ShareObject::Album,
+        "1065973699",
+        "DE",
+    ),
+    (
+        "https://MUSIC.APPLE.COM/gb/song/1810905307",
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1810905307",
+        "GB",
+    ),
+    (
+        "https://music.apple.com/us/playlist/todays-hits/pl.f4d106fed2bd41149aaacabb233eb5eb",
+        LinkType::AppleMusic,
+        ShareObject::Playlist,
+        "pl.f4d106fed2bd41149aaacabb233eb5eb",
+        "US",
+    ),
+    (
+        "https://music.apple.com/de/album/virgin/1810905299?i=1810905307",
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1810905307",
+        "DE",
+    ),
+    (
+        "https://music.apple.com/jp/album/1810905299?l=en-US&i=1810905307",
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1810905307",
+        "JP",
+    ),
+    (
+        "https://geo.music.apple.com/gb/album/virgin/1810905299?app=music",
+        LinkType::AppleMusic,
+        ShareObject::Album,
+        "1810905299",
+        "GB",
+    ),
+    (
+        "https://geo.music.apple.com/album/virgin/1810905299?i=1810905307",
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1810905307",
+        "US",
+    ),
+    (
+        "https://itunes.apple.com/fr/album/the-dark-side-of-the-moon/id1065973699",
+        LinkType::AppleMusic,
+        ShareObject::Album,
+        "1065973699",
+        "FR",
+    ),
+    (
+        "https://itunes.apple.com/us/album/the-dark-side-of-the-moon/id1065973699?i=1065973975&uo=4",
+        LinkType::AppleMusic,
+        ShareObject::Song,
+        "1065973975",
+        "US",
+    ),
+    (
+        "https://itunes.apple.com/de/artist/pink-floyd/id487143",
+        LinkType::AppleMusic,
+        ShareObject::Artist,
+        "487143",
+        "DE",
+    ),
+];
+
+#[tokio::test]
+async fn parse_real_world_share_links() {
+    for (url, link_type, share_obj, id, country) in SHARE_LINKS {
+        let link = ShareLink::from_url(url)
+            .await
+            .unwrap_or_else(|err| panic!("{} could not be parsed: {}", url, err));
+        assert_eq!(
+            (link.link_type, link.share_obj, link.id.as_str()),
+            (*link_type, *share_obj, *id),
+            "{}",
+            url
+        );
+        assert_eq!(link.country_code.alpha2, *country, "{}", url);
+    }
+}
+
+#[tokio::test]
+async fn reject_links_that_are_no_share_links() {
+    let invalid_urls = [
+        "open.spotify.com/track/1",
+        "ftp://open.spotify.com/track/1",
+        "",
+    ];
+    for url in invalid_urls {
+        assert!(
+            matches!(
+                ShareLink::from_url(url).await,
+                Err(ShareLinkError::InvalidUrl)
+            ),
+            "{}",
+            url
+        );
+    }
+
+    let other_hosts = [
+        "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+        "https://spotify.com.example.com/track/1",
+        "https://soundcloud.com/artist/track",
+    ];
+    for url in other_hosts {
+        assert!(
+            matches!(
+                ShareLink::from_url(url).await,
+                Err(ShareLinkError::NotAShareLink)
+            ),
+            "{}",
+            url
+        );
+    }
+
+    let malformed_links = [
+        "https://open.spotify.com/",
+        "https://open.spotify.com/show/1",
+        "https://open.spotify.com/track/",
+        "https://open.spotify.com/intl-de/track",
+        "https://tidal.com/browse/video/1",
+        "https://www.deezer.com/track/1/2/3",
+        "https://www.deezer.com/search/track/1",
+        "https://music.apple.com/zz/album/name/1",
+        "https://music.apple.com/de/album/name/1?i=",
+        "https://itunes.apple.com/us/album/name/id",
+        "https://open.spotify.com/track/a%20b",
+    ];
+    for url in malformed_links {
+        assert!(
+            matches!(
+                ShareLink::from_url(url).await,
+                Err(ShareLinkError::MalformedOrInvalidLink)
+            ),
+            "{}",
+            url
+        );
+    }
+}
+
+#[tokio::test]
+async fn parse_app_links() {
+    let app_links = [
+        (
+            "spotify:track:36puuD04lEUD8kVwQsTLm6",
+            LinkType::Spotify,
+            ShareObject::Song,
+            "36puuD04lEUD8kVwQsTLm6",
+        ),
+        (
+            "spotify:album:4LH4d3cOWNNsVw41Gqt2kv",
+            LinkType::Spotify,
+            ShareObject::Album,
+            "4LH4d3cOWNNsVw41Gqt2kv",
+        ),
+        (
+            "spotify:user:spotify:playlist:37i9dQZF1DXcBWIGoYBM5M",
+            LinkType::Spotify,
+            ShareObject::Playlist,
+            "37i9dQZF1DXcBWIGoYBM5M",
+        ),
+        (
+            "tidal://track/300807510",
+            LinkType::Tidal,
+            ShareObject::Song,
+            "300807510",
+        ),
+        (
+            "tidal://browse/album/412502324",
+            LinkType::Tidal,
+            ShareObject::Album,
+            "412502324",
+        ),
+        (
+            "deezer://www.deezer.com/track/3135556",
+            LinkType::Deezer,
+            ShareObject::Song,
+            "3135556",
+        ),
+        (
+            "deezer://www.deezer.com/fr/artist/27",
+            LinkType::Deezer,
+            ShareObject::Artist,
+            "27",
+        ),
+    ];
+    for (url, link_type, share_obj, id) in app_links {
+        let link = ShareLink::from_url(url)
+            .await
+            .unwrap_or_else(|err| panic!("{} could not be parsed: {}", url, err));
+        assert_eq!(
+            (link.link_type, link.share_obj, link.id.as_str()),
+            (link_type, share_obj, id),
+            "{}",
+            url
+        );
+    }
+
+    let malformed_links = [
+        "spotify:track",
+        "spotify:episode:1",
+        "tidal://track",
+        "deezer://www.example.com/track/1",
+    ];
+    for url in malformed_links {
+        assert!(
+            matches!(
+                ShareLink::from_url(url).await,
+                Err(ShareLinkError::MalformedOrInvalidLink)
+            ),
+            "{}",
+            url
+        );
+    }
+}
+
+#[tokio::test]
+async fn app_uris_are_parsed_into_the_same_link() {
+    let de = rust_iso3166::from_alpha2("DE").unwrap();
+    for link_type in [LinkType::Spotify, LinkType::Tidal, LinkType::Deezer] {
+        for share_obj in ShareObject::ALL {
+            let link = ShareLink::new(link_type, share_obj, "123", &de);
+            let parsed = ShareLink::from_url_with_default_country(&link.to_app_uri(), &de)
+                .await
+                .unwrap();
+            assert_eq!(parsed, link);
+        }
+    }
+
+    let apple_music = ShareLink::new(LinkType::AppleMusic, ShareObject::Song, "123", &de);
+    assert_eq!(
+        apple_music.to_app_uri(),
+        "music://music.apple.com/de/song/123"
+    );
+}
+
+#[tokio::test]
+async fn links_of_every_link_format_are_parsed_into_the_same_link() {
+    let de = rust_iso3166::from_alpha2("DE").unwrap();
+    for format in LINK_FORMATS {
+        assert_eq!(LinkType::from_name(format.name()), Some(format.link_type()));
+        for share_obj in ShareObject::ALL {
+            let link = ShareLink::new(format.link_type(), share_obj, "123", &de);
+            let parsed = ShareLink::from_url_with_default_country(&link.to_url(), &de)
+                .await
+                .unwrap();
+            assert_eq!(parsed, link);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::{ErrorReason, PlaylistConversionResults, convert},
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    share_link::{LinkType, ShareLink, ShareObject},
+    shared_item::{Data, PlaylistData, SongData},
+};
+use rust_iso3166::CountryCode;
+
+use common::{SingleSongProvider, api_clients};
+
+/// Knows a playlist with the single song, a song it does not have and a local file, and converts
+/// playlists track by track.
+struct PlaylistProvider(SingleSongProvider);
+
+impl PlaylistProvider {
+    fn playlist() -> PlaylistData {
+        PlaylistData::new(
+            "Covers",
+            vec![
+                SingleSongProvider::song(),
+                SongData::new("Unreleased", "XX0000000000", 200, Vec::new(), Vec::new()),
+                SongData::new("Demo.mp3", "", 120, Vec::new(), Vec::new()),
+            ],
+        )
+    }
+}
+
+#[async_trait]
+impl MusicProvider for PlaylistProvider {
+    fn info(&self) -> ProviderInfo {
+        ProviderInfo {
+            parses: &[ShareObject::Playlist],
+            converts_to: &[ShareObject::Song, ShareObject::Playlist],
+            ..self.0.info()
+        }
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        match link.share_obj {
+            ShareObject::Playlist => Ok(Data::Playlist(Self::playlist())),
+            _ => Err(ApiError::UnsupportedFeature),
+        }
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        self.0.data_to_link(data, country_code).await
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Ok(String::new())
+    }
+}
+
+/// Converts playlists, but rejects its credentials.
+struct UnauthorizedProvider(PlaylistProvider);
+
+#[async_trait]
+impl MusicProvider for UnauthorizedProvider {
+    fn info(&self) -> ProviderInfo {
+        self.0.info()
+    }
+
+    async fn link_to_data(&self, _link: &ShareLink) -> Result<Data, ApiError> {
+        Err(ApiError::Unauthorized)
+    }
+
+    async fn data_to_link(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        Err(ApiError::Unauthorized)
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Err(ApiError::Unauthorized)
+    }
+}
+
+#[tokio::test]
+async fn playlists_are_converted_track_by_track() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(PlaylistProvider(SingleSongProvider::new(LinkType::Deezer)));
+    registry.register(PlaylistProvider(SingleSongProvider::new(LinkType::Tidal)));
+    registry.register(UnauthorizedProvider(PlaylistProvider(
+        SingleSongProvider::new(LinkType::Spotify),
+    )));
+
+    let conversion = convert("https://www.deezer.com/playlist/1", api_clients(registry))
+        .await
+        .unwrap();
+    let conversion: PlaylistConversionResults = serde_json::from_str(&conversion).unwrap();
+
+    let providers: Vec<&str> = conversion
+        .results
+        .iter()
+        .map(|playlist| playlist.provider.as_str())
+        .collect();
+    assert_eq!(providers, vec!["Deezer", "Tidal"]);
+    let tidal = &conversion.results[1];
+    assert_eq!(tidal.display_name, "Covers");
+    let tracks: Vec<&str> = tidal.tracks.iter().map(|link| link.url.as_str()).collect();
+    assert_eq!(tracks, vec!["https://tidal.com/browse/track/1"]);
+    let unmatched: Vec<(&str, &str)> = tidal
+        .unmatched
+        .iter()
+        .map(|track| (track.display_name.as_str(), track.isrc.as_str()))
+        .collect();
+    assert_eq!(
+        unmatched,
+        vec![("Unreleased", "XX0000000000"), ("Demo.mp3", "")]
+    );
+
+    assert_eq!(conversion.errors.len(), 1);
+    assert_eq!(conversion.errors[0].provider, "Spotify");
+    assert_eq!(conversion.errors[0].reason, ErrorReason::AuthFailure);
+    assert!(!conversion.truncated);
+}
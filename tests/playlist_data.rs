@@ -0,0 +1,27 @@
+use interlude::shared_item::{PlaylistData, SongData};
+
+fn playlist(length: usize) -> PlaylistData {
+    let songs = (0..length)
+        .map(|i| SongData::new(&format!("Song {}", i), "", 180, Vec::new(), Vec::new()))
+        .collect();
+    PlaylistData::new("My Playlist", songs)
+}
+
+#[test]
+fn long_playlists_are_truncated() {
+    let mut long_playlist = playlist(101);
+    long_playlist.truncate(100);
+
+    assert_eq!(long_playlist.songs.len(), 100);
+    assert_eq!(long_playlist.songs[99].display_name, "Song 99");
+    assert!(long_playlist.truncated);
+}
+
+#[test]
+fn short_playlists_are_not_truncated() {
+    let mut short_playlist = playlist(100);
+    short_playlist.truncate(100);
+
+    assert_eq!(short_playlist.songs.len(), 100);
+    assert!(!short_playlist.truncated);
+}
@@ -0,0 +1,362 @@
+mod common;
+
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::{
+            ConversionError, ConversionOptions, ConversionResults, ErrorReason, MatchMethod,
+            convert, convert_with_options,
+        },
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    server::public_utils::get_providers,
+    share_link::{LinkType, ShareLink},
+    shared_item::Data,
+};
+use rust_iso3166::CountryCode;
+
+use common::{SingleSongProvider, api_clients, api_clients_in};
+
+#[test]
+fn registering_a_provider_again_replaces_it() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    registry.register(SingleSongProvider::new(LinkType::Tidal));
+    registry.register(SingleSongProvider {
+        url: "https://www.deezer.com",
+        ..SingleSongProvider::new(LinkType::Deezer)
+    });
+
+    let link_types: Vec<LinkType> = registry
+        .iter()
+        .map(|provider| provider.info().link_type)
+        .collect();
+    assert_eq!(link_types, vec![LinkType::Tidal, LinkType::Deezer]);
+    assert_eq!(
+        registry.get(LinkType::Deezer).unwrap().info().url,
+        "https://www.deezer.com"
+    );
+    assert!(registry.get(LinkType::Spotify).is_none());
+}
+
+#[tokio::test]
+async fn conversions_query_every_registered_provider() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Tidal));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    let api_clients = api_clients(registry);
+
+    let conversion = convert("https://www.deezer.com/track/1", api_clients.clone())
+        .await
+        .unwrap();
+    let conversion: ConversionResults = serde_json::from_str(&conversion).unwrap();
+
+    let providers: Vec<&str> = conversion
+        .results
+        .iter()
+        .map(|link| link.provider.as_str())
+        .collect();
+    assert_eq!(providers, vec!["Tidal", "Deezer"]);
+    assert!(
+        conversion
+            .results
+            .iter()
+            .all(|link| link.matched_by == Some(MatchMethod::Isrc))
+    );
+    assert!(conversion.errors.is_empty());
+    assert!(api_clients.is_available(LinkType::Tidal));
+    assert!(!api_clients.is_available(LinkType::Spotify));
+}
+
+fn result_providers(conversion: &str) -> Vec<String> {
+    let conversion: ConversionResults = serde_json::from_str(conversion).unwrap();
+    conversion
+        .results
+        .into_iter()
+        .map(|link| link.provider)
+        .collect()
+}
+
+fn three_providers() -> ProviderRegistry {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Spotify));
+    registry.register(SingleSongProvider::new(LinkType::Tidal));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    registry
+}
+
+#[tokio::test]
+async fn conversions_can_be_restricted_and_ordered() {
+    let api_clients = api_clients(three_providers());
+    let link = "https://www.deezer.com/track/1";
+
+    let options = ConversionOptions {
+        to: Some(vec![LinkType::Deezer, LinkType::Tidal]),
+        ..Default::default()
+    };
+    let conversion = convert_with_options(link, &options, api_clients.clone())
+        .await
+        .unwrap();
+    assert_eq!(result_providers(&conversion), vec!["Deezer", "Tidal"]);
+
+    let options = ConversionOptions {
+        order: vec![LinkType::Deezer],
+        ..Default::default()
+    };
+    let conversion = convert_with_options(link, &options, api_clients.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        result_providers(&conversion),
+        vec!["Deezer", "Spotify", "Tidal"]
+    );
+
+    let options = ConversionOptions {
+        to: Some(vec![LinkType::Spotify, LinkType::Tidal]),
+        order: vec![LinkType::Tidal],
+        ..Default::default()
+    };
+    let conversion = convert_with_options(link, &options, api_clients)
+        .await
+        .unwrap();
+    assert_eq!(result_providers(&conversion), vec!["Tidal", "Spotify"]);
+}
+
+#[tokio::test]
+async fn restricted_conversions_do_not_hide_providers_from_later_ones() {
+    let api_clients = api_clients(three_providers());
+    let link = "https://www.deezer.com/track/1";
+
+    let options = ConversionOptions {
+        to: Some(vec![LinkType::Tidal]),
+        ..Default::default()
+    };
+    convert_with_options(link, &options, api_clients.clone())
+        .await
+        .unwrap();
+
+    let conversion = convert(link, api_clients.clone()).await.unwrap();
+    assert_eq!(
+        result_providers(&conversion),
+        vec!["Spotify", "Tidal", "Deezer"]
+    );
+
+    // now answered from the cache
+    let conversion = convert_with_options(link, &options, api_clients)
+        .await
+        .unwrap();
+    assert_eq!(result_providers(&conversion), vec!["Tidal"]);
+}
+
+fn result_urls(conversion: &str) -> Vec<String> {
+    let conversion: ConversionResults = serde_json::from_str(conversion).unwrap();
+    conversion
+        .results
+        .into_iter()
+        .map(|link| link.url)
+        .collect()
+}
+
+fn tidal_and_apple_music() -> ProviderRegistry {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Tidal));
+    registry.register(SingleSongProvider::new(LinkType::AppleMusic));
+    registry
+}
+
+#[tokio::test]
+async fn links_without_country_are_converted_in_the_default_country() {
+    let api_clients = api_clients_in(tidal_and_apple_music(), "gb");
+
+    let conversion = convert("https://tidal.com/browse/track/1", api_clients)
+        .await
+        .unwrap();
+    assert_eq!(
+        result_urls(&conversion),
+        vec![
+            "https://tidal.com/browse/track/1",
+            "https://music.apple.com/gb/song/1"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn country_overrides_the_country_of_the_link() {
+    let api_clients = api_clients(tidal_and_apple_music());
+    let link = "https://music.apple.com/us/song/hurt/1";
+
+    let options = ConversionOptions {
+        country: rust_iso3166::from_alpha2("JP"),
+        ..Default::default()
+    };
+    let conversion = convert_with_options(link, &options, api_clients.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        result_urls(&conversion)[1],
+        "https://music.apple.com/jp/song/1"
+    );
+
+    let conversion = convert(link, api_clients).await.unwrap();
+    assert_eq!(
+        result_urls(&conversion)[1],
+        "https://music.apple.com/us/song/1"
+    );
+}
+
+#[tokio::test]
+async fn links_of_unregistered_providers_are_not_supported() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+
+    let result = convert("https://tidal.com/browse/track/1", api_clients(registry)).await;
+    assert!(matches!(
+        result,
+        Err(ConversionError::ApiClient(ApiError::UnsupportedFeature))
+    ));
+}
+
+#[tokio::test]
+async fn links_the_provider_can_not_parse_are_not_supported() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+
+    let result = convert("https://www.deezer.com/album/1", api_clients(registry)).await;
+    assert!(matches!(
+        result,
+        Err(ConversionError::ApiClient(ApiError::UnsupportedFeature))
+    ));
+}
+
+#[tokio::test]
+async fn providers_that_can_not_convert_the_item_are_skipped() {
+    struct ParseOnly(SingleSongProvider);
+
+    #[async_trait]
+    impl MusicProvider for ParseOnly {
+        fn info(&self) -> ProviderInfo {
+            ProviderInfo {
+                converts_to: &[],
+                ..self.0.info()
+            }
+        }
+
+        async fn link_to_data(&self, _link: &ShareLink) -> Result<Data, ApiError> {
+            panic!("The provider should not be queried.")
+        }
+
+        async fn data_to_link(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<ShareLink, ApiError> {
+            panic!("The provider should not be queried.")
+        }
+
+        async fn get_artwork(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<String, ApiError> {
+            panic!("The provider should not be queried.")
+        }
+    }
+
+    let mut registry = ProviderRegistry::new();
+    registry.register(ParseOnly(SingleSongProvider::new(LinkType::Tidal)));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+
+    let conversion = convert("https://www.deezer.com/track/1", api_clients(registry))
+        .await
+        .unwrap();
+    let conversion: ConversionResults = serde_json::from_str(&conversion).unwrap();
+
+    assert_eq!(conversion.results.len(), 1);
+    assert_eq!(conversion.errors.len(), 1);
+    assert_eq!(conversion.errors[0].provider, "Tidal");
+    assert_eq!(conversion.errors[0].reason, ErrorReason::Unsupported);
+}
+
+#[test]
+fn providers_list_their_capabilities() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+
+    let providers =
+        serde_json::to_value(get_providers("example.com", &api_clients(registry))).unwrap();
+    assert_eq!(
+        providers,
+        serde_json::json!([{
+            "name": "Deezer",
+            "url": "https://example.com",
+            "logoUrl": "example.com/public/example_logo.png",
+            "iconUrl": "example.com/public/example_icon.png",
+            "parses": ["song"],
+            "convertsTo": ["song", "album"],
+            "available": true
+        }])
+    );
+}
+
+#[tokio::test]
+async fn results_with_missing_items_are_not_cached() {
+    /// Never finds the song, e.g. because its quota is exceeded, and counts how often it is asked.
+    struct MissingSongProvider(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl MusicProvider for MissingSongProvider {
+        fn info(&self) -> ProviderInfo {
+            SingleSongProvider::new(LinkType::Tidal).info()
+        }
+
+        async fn link_to_data(&self, _link: &ShareLink) -> Result<Data, ApiError> {
+            Err(ApiError::UnsuccessfulConversion)
+        }
+
+        async fn data_to_link(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<ShareLink, ApiError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Err(ApiError::UnsuccessfulConversion)
+        }
+
+        async fn search_data_to_link(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<ShareLink, ApiError> {
+            Err(ApiError::UnsuccessfulConversion)
+        }
+
+        async fn get_artwork(
+            &self,
+            _data: &Data,
+            _country_code: &CountryCode,
+        ) -> Result<String, ApiError> {
+            Ok(String::new())
+        }
+    }
+
+    let queries = Arc::new(AtomicUsize::new(0));
+    let mut registry = ProviderRegistry::new();
+    registry.register(MissingSongProvider(queries.clone()));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    let api_clients = api_clients(registry);
+
+    for _ in 0..2 {
+        let conversion = convert("https://www.deezer.com/track/1", api_clients.clone())
+            .await
+            .unwrap();
+        let conversion: ConversionResults = serde_json::from_str(&conversion).unwrap();
+        assert_eq!(conversion.errors[0].reason, ErrorReason::NotFound);
+    }
+    assert_eq!(queries.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use interlude::{
+    api::{
+        ApiError,
+        conversion::{ApiClients, ConversionResults, ErrorReason, convert},
+        provider::{MusicProvider, ProviderInfo, ProviderRegistry},
+    },
+    config::Config,
+    share_link::{LinkType, ShareLink},
+    shared_item::Data,
+};
+use rust_iso3166::CountryCode;
+
+use common::SingleSongProvider;
+
+/// Finds the song only after a long time.
+struct SlowProvider(SingleSongProvider);
+
+#[async_trait]
+impl MusicProvider for SlowProvider {
+    fn info(&self) -> ProviderInfo {
+        self.0.info()
+    }
+
+    async fn link_to_data(&self, link: &ShareLink) -> Result<Data, ApiError> {
+        self.0.link_to_data(link).await
+    }
+
+    async fn data_to_link(
+        &self,
+        data: &Data,
+        country_code: &CountryCode,
+    ) -> Result<ShareLink, ApiError> {
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        self.0.data_to_link(data, country_code).await
+    }
+
+    async fn get_artwork(
+        &self,
+        _data: &Data,
+        _country_code: &CountryCode,
+    ) -> Result<String, ApiError> {
+        Ok(String::new())
+    }
+}
+
+#[tokio::test]
+async fn slow_providers_time_out_without_holding_back_the_others() {
+    let mut registry = ProviderRegistry::new();
+    registry.register(SlowProvider(SingleSongProvider::new(LinkType::Tidal)));
+    registry.register(SlowProvider(SingleSongProvider::new(LinkType::Spotify)));
+    registry.register(SingleSongProvider::new(LinkType::AppleMusic));
+    registry.register(SingleSongProvider::new(LinkType::Deezer));
+    let config = Config {
+        database_path: String::from(":memory:"),
+        provider_timeout: 1,
+        ..Config::default()
+    };
+    let api_clients = Arc::new(ApiClients::with_providers(registry, &config));
+
+    let start = Instant::now();
+    let conversion = convert("https://www.deezer.com/track/1", api_clients)
+        .await
+        .unwrap();
+    let elapsed = start.elapsed();
+    let conversion: ConversionResults = serde_json::from_str(&conversion).unwrap();
+
+    // the targets are queried concurrently, so both slow ones time out together
+    assert!(elapsed >= Duration::from_secs(1));
+    assert!(elapsed < Duration::from_secs(2));
+    let providers: Vec<&str> = conversion
+        .results
+        .iter()
+        .map(|link| link.provider.as_str())
+        .collect();
+    assert_eq!(providers, vec!["AppleMusic", "Deezer"]);
+    let errors: Vec<(&str, ErrorReason)> = conversion
+        .errors
+        .iter()
+        .map(|err| (err.provider.as_str(), err.reason))
+        .collect();
+    assert_eq!(
+        errors,
+        vec![
+            ("Tidal", ErrorReason::Timeout),
+            ("Spotify", ErrorReason::Timeout)
+        ]
+    );
+}
@@ -0,0 +1,117 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use interlude::api::{ApiError, request::RequestExt};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Serves the given raw HTTP responses one after another, repeating the last one, and returns
+/// the URL of the server together with the number of requests it received.
+async fn serve(responses: &[&'static str]) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let responses = responses.to_vec();
+
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let index = counter.fetch_add(1, Ordering::SeqCst).min(responses.len() - 1);
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer).await;
+            stream.write_all(responses[index].as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+const OK: &str = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}";
+const UNAVAILABLE: &str =
+    "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const UNAUTHORIZED: &str =
+    "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const RATE_LIMITED: &str = "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 60\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+// Deezer reports its errors with status 200
+const QUOTA_EXCEEDED: &str =
+    "HTTP/1.1 200 OK\r\nContent-Length: 20\r\nConnection: close\r\n\r\n{\"error\":{\"code\":4}}";
+const INVALID_TOKEN: &str =
+    "HTTP/1.1 200 OK\r\nContent-Length: 22\r\nConnection: close\r\n\r\n{\"error\":{\"code\":300}}";
+const NO_DATA: &str =
+    "HTTP/1.1 200 OK\r\nContent-Length: 22\r\nConnection: close\r\n\r\n{\"error\":{\"code\":800}}";
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let (url, requests) = serve(&[UNAVAILABLE, OK]).await;
+
+    let body = reqwest::Client::new().get(url).send_with_retry().await.unwrap();
+
+    assert_eq!(body, "{}");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn retries_are_bounded() {
+    let (url, requests) = serve(&[UNAVAILABLE]).await;
+
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+
+    assert!(matches!(result, Err(ApiError::UnexpectedStatus(503))));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn long_retry_after_is_not_waited_for() {
+    let (url, requests) = serve(&[RATE_LIMITED]).await;
+
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+
+    assert!(matches!(
+        result,
+        Err(ApiError::RateLimited {
+            retry_after: Some(60)
+        })
+    ));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (url, requests) = serve(&[UNAUTHORIZED]).await;
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+    assert!(matches!(result, Err(ApiError::Unauthorized)));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let (url, requests) = serve(&[NOT_FOUND]).await;
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+    assert!(matches!(result, Err(ApiError::UnexpectedStatus(404))));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn errors_in_successful_responses_are_reported() {
+    let (url, requests) = serve(&[QUOTA_EXCEEDED]).await;
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+    assert!(matches!(result, Err(ApiError::RateLimited { retry_after: None })));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    let (url, requests) = serve(&[QUOTA_EXCEEDED, OK]).await;
+    let body = reqwest::Client::new().get(url).send_with_retry().await.unwrap();
+    assert_eq!(body, "{}");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let (url, requests) = serve(&[INVALID_TOKEN]).await;
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+    assert!(matches!(result, Err(ApiError::Unauthorized)));
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    let (url, _) = serve(&[NO_DATA]).await;
+    let result = reqwest::Client::new().get(url).send_with_retry().await;
+    assert!(matches!(result, Err(ApiError::UnsuccessfulConversion)));
+}
@@ -0,0 +1,124 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use interlude::share_link::{ShareLinkError, redirect::RedirectResolver};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Serves the given raw HTTP responses one after another, repeating the last one, and returns
+/// the URL of the server together with the number of requests it received.
+async fn serve(responses: &[String]) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let responses = responses.to_vec();
+
+    let counter = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let index = counter
+                .fetch_add(1, Ordering::SeqCst)
+                .min(responses.len() - 1);
+            let mut buffer = [0; 1024];
+            let _ = stream.read(&mut buffer).await;
+            stream.write_all(responses[index].as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+fn redirect(location: &str) -> String {
+    format!(
+        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        location
+    )
+}
+
+fn local_resolver() -> RedirectResolver {
+    RedirectResolver::new(&["127.0.0.1"], 3, Duration::from_millis(500))
+}
+
+#[tokio::test]
+async fn redirects_are_followed_to_the_share_link() {
+    let (url, requests) = serve(&[
+        redirect("/second-hop"),
+        redirect("https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6?si=1"),
+    ])
+    .await;
+
+    let resolved = local_resolver().resolve(&url).await.unwrap();
+
+    assert_eq!(
+        resolved,
+        "https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6?si=1"
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn redirects_are_bounded() {
+    let (url, requests) = serve(&[redirect("/again")]).await;
+
+    let result = local_resolver().resolve(&url).await;
+
+    assert!(matches!(result, Err(ShareLinkError::UnresolvableShortLink)));
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn short_links_that_do_not_redirect_are_unresolvable() {
+    let (url, _) = serve(&[String::from(
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+    )])
+    .await;
+
+    let result = local_resolver().resolve(&url).await;
+
+    assert!(matches!(result, Err(ShareLinkError::UnresolvableShortLink)));
+}
+
+#[tokio::test]
+async fn slow_short_links_time_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (_stream, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let resolver = RedirectResolver::new(&["127.0.0.1"], 3, Duration::from_millis(100));
+    let result = resolver.resolve(&url).await;
+
+    assert!(matches!(result, Err(ShareLinkError::UnresolvableShortLink)));
+}
+
+#[tokio::test]
+async fn dynamic_links_are_resolved_without_a_request() {
+    let url =
+        "https://dzr.page.link/?link=https%3A%2F%2Fwww.deezer.com%2Ftrack%2F3135556%3Fhost%3D0";
+
+    let resolved = RedirectResolver::default().resolve(url).await.unwrap();
+
+    assert_eq!(resolved, "https://www.deezer.com/track/3135556?host=0");
+}
+
+#[tokio::test]
+async fn other_urls_are_not_requested() {
+    let resolver = RedirectResolver::default();
+    for url in [
+        "https://music.apple.com/de/album/größe/1?i=2",
+        "https://open.spotify.com/track/36puuD04lEUD8kVwQsTLm6",
+        "not a url",
+    ] {
+        assert_eq!(resolver.resolve(url).await.unwrap(), url);
+    }
+}
@@ -0,0 +1,59 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use interlude::api::conversion::single_flight::SingleFlight;
+
+#[tokio::test]
+async fn concurrent_calls_share_one_result() {
+    let single_flight: Arc<SingleFlight<&str, String>> = Arc::new(SingleFlight::new());
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let mut handles = Vec::new();
+    for _ in 0..10 {
+        let single_flight = single_flight.clone();
+        let calls = calls.clone();
+        handles.push(tokio::spawn(async move {
+            single_flight
+                .run("spotify:track:1", || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Ok::<_, ()>(String::from("converted"))
+                })
+                .await
+        }));
+    }
+    for handle in handles {
+        assert_eq!(handle.await.unwrap(), Ok(String::from("converted")));
+    }
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(single_flight.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn finished_calls_are_not_reused() {
+    let single_flight: SingleFlight<&str, usize> = SingleFlight::new();
+
+    let first = single_flight.run("key", || async { Ok::<_, ()>(1) }).await;
+    let second = single_flight.run("key", || async { Ok::<_, ()>(2) }).await;
+
+    assert_eq!(first, Ok(1));
+    assert_eq!(second, Ok(2));
+}
+
+#[tokio::test]
+async fn errors_are_not_shared() {
+    let single_flight: SingleFlight<&str, usize> = SingleFlight::new();
+
+    let failed = single_flight.run("key", || async { Err("timeout") }).await;
+    let retried = single_flight.run("key", || async { Ok::<_, &str>(1) }).await;
+
+    assert_eq!(failed, Err("timeout"));
+    assert_eq!(retried, Ok(1));
+    assert_eq!(single_flight.in_flight(), 0);
+}